@@ -0,0 +1,178 @@
+//! Command line interface for approximate network matching and interpolation
+use anime::{
+    builder::{AnimeBuilder, CrsMode},
+    interpolate::Tensive,
+    io::{
+        geojson::{read_geojson_file, write_geojson},
+        LineFeatures,
+    },
+    Anime, Side,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "anime",
+    version,
+    about = "Approximate Network Matching, Integration, and Enrichment"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Match two sets of lines and write the match table as CSV
+    Match {
+        #[command(flatten)]
+        args: MatchArgs,
+    },
+    /// Interpolate a source attribute onto the target lines
+    Interpolate {
+        #[command(flatten)]
+        args: MatchArgs,
+        /// Numeric source attribute to interpolate
+        #[arg(short, long)]
+        column: String,
+        /// Name of the interpolated attribute. Defaults to `column`
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Args)]
+struct MatchArgs {
+    /// GeoJSON file of source lines
+    #[arg(short, long)]
+    source: PathBuf,
+    /// GeoJSON file of target lines
+    #[arg(short, long)]
+    target: PathBuf,
+    /// Maximum distance between two lines to be considered a match
+    #[arg(short, long, default_value_t = 10.0)]
+    distance_tolerance: f64,
+    /// Maximum angle in degrees between two lines to be considered a match
+    #[arg(short, long, default_value_t = 5.0)]
+    angle_tolerance: f64,
+    /// Coordinate reference system of both files
+    ///
+    /// With `geographic` the lines are in longitude and latitude, and the
+    /// distance tolerance and shared lengths are in meters.
+    #[arg(long, value_enum, default_value_t = CrsArg::Projected)]
+    crs: CrsArg,
+    /// Extensive or intensive interpolation
+    ///
+    /// For `match` this determines the `weight` column: the shared length
+    /// divided by the source length (extensive) or the target length (intensive).
+    #[arg(long, value_enum, default_value_t = TensiveArg::Ex)]
+    tensive: TensiveArg,
+    /// Output file. Writes to stdout if not provided
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TensiveArg {
+    /// Length weighted sum
+    Ex,
+    /// Length weighted mean
+    In,
+}
+
+impl From<TensiveArg> for Tensive {
+    fn from(value: TensiveArg) -> Self {
+        match value {
+            TensiveArg::Ex => Tensive::Ex,
+            TensiveArg::In => Tensive::In,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CrsArg {
+    /// Planar coordinates in the units of the CRS
+    Projected,
+    /// Longitude and latitude in degrees
+    Geographic,
+}
+
+impl From<CrsArg> for CrsMode {
+    fn from(value: CrsArg) -> Self {
+        match value {
+            CrsArg::Projected => CrsMode::Projected,
+            CrsArg::Geographic => CrsMode::Geographic,
+        }
+    }
+}
+
+fn open_output(output: &Option<PathBuf>) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    Ok(match output {
+        Some(p) => Box::new(BufWriter::new(File::create(p)?)),
+        None => Box::new(BufWriter::new(std::io::stdout())),
+    })
+}
+
+/// Read both files and match them, returning the source and target features
+fn match_lines(args: &MatchArgs) -> Result<(Anime, LineFeatures, LineFeatures), Box<dyn Error>> {
+    let source = read_geojson_file(&args.source).map_err(|e| e.with_side(Side::Source))?;
+    let target = read_geojson_file(&args.target).map_err(|e| e.with_side(Side::Target))?;
+    let anime = AnimeBuilder::new(
+        source.geometries.iter().cloned(),
+        target.geometries.iter().cloned(),
+    )
+    .distance_tolerance(args.distance_tolerance)
+    .angle_tolerance(args.angle_tolerance)
+    .crs_mode(args.crs.into())
+    .find_matches()?
+    .into_anime();
+    Ok((anime, source, target))
+}
+
+fn run_match(args: MatchArgs) -> Result<(), Box<dyn Error>> {
+    let (anime, _, _) = match_lines(&args)?;
+    let mut out = open_output(&args.output)?;
+    writeln!(out, "target_id,source_id,shared_len,weight,confidence")?;
+    for row in anime.match_table()? {
+        let weight = match Tensive::from(args.tensive) {
+            Tensive::Ex => row.source_weighted,
+            Tensive::In => row.target_weighted,
+        };
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            row.target_id, row.source_id, row.shared_len, weight, row.confidence
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn run_interpolate(
+    args: MatchArgs,
+    column: String,
+    name: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let (anime, source, mut target) = match_lines(&args)?;
+    let values = anime.interpolate_column(&source, &column, args.tensive.into())?;
+    target.set_column(name.unwrap_or(column), values)?;
+    write_geojson(&target, open_output(&args.output)?)?;
+    Ok(())
+}
+
+fn main() {
+    let res = match Cli::parse().command {
+        Command::Match { args } => run_match(args),
+        Command::Interpolate { args, column, name } => run_interpolate(args, column, name),
+    };
+    if let Err(e) = res {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
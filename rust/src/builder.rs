@@ -0,0 +1,551 @@
+use crate::{
+    add_candidate, create_source_rtree, create_target_rtree,
+    descriptor::LineDescriptor,
+    find_candidate_matches_with,
+    interpolate::{InterpolatedValue, Tensive},
+    model::MatchModel,
+    pair_shared_len,
+    preprocess::Preprocess,
+    table::MatchRow,
+    tolerance::FeatureTolerances,
+    Anime, AnimeError, MatchesMap, PairOptions, SourceTree, TargetTree,
+};
+use geo::{BoundingRect, Euclidean, Length, MapCoords};
+use geo_types::{Coord, MultiLineString, Rect};
+use rstar::RTreeObject;
+use std::{num::NonZeroUsize, sync::OnceLock, thread};
+
+/// Mean radius of the earth in meters
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// How the shared length of two component lines is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverlapMethod {
+    /// Overlap of the x or y ranges solved along the source line
+    ///
+    /// The x range is used for lines that are closer to horizontal and the
+    /// y range otherwise.
+    #[default]
+    Axis,
+    /// Length of the source line between the orthogonal projections of the
+    /// target end points
+    Projection,
+}
+
+/// The coordinate reference system of the geometries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CrsMode {
+    /// Planar coordinates. Tolerances and lengths are in the units of the CRS
+    #[default]
+    Projected,
+    /// Longitude and latitude in degrees
+    ///
+    /// Geometries are projected to an equirectangular projection centered on
+    /// the latitude of their combined bounding box. The distance tolerance and
+    /// all lengths are in meters. This is only suitable for regional extents.
+    Geographic,
+}
+
+/// Whether the direction of the lines is considered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DirectionMode {
+    /// Lines match regardless of the direction they were digitized in
+    #[default]
+    Undirected,
+    /// Lines only match if they point the same way, e.g. for one-way carriageways
+    Directed,
+}
+
+/// How many threads are used to load the geometries and find matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Parallelism {
+    /// A single thread
+    #[default]
+    Sequential,
+    /// Candidate matches are found with this many threads
+    ///
+    /// The candidates of each target may be in a different order than
+    /// when found sequentially.
+    Threads(NonZeroUsize),
+}
+
+/// Options used by [`AnimeBuilder`]
+///
+/// The options are stored on the resulting [`Anime`] as [`Anime::options`].
+/// The distance and angle tolerances are stored on the [`Anime`] directly.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchOptions {
+    pub overlap_method: OverlapMethod,
+    pub crs_mode: CrsMode,
+    pub direction_mode: DirectionMode,
+    pub parallelism: Parallelism,
+    /// Densify or simplify the geometries before they are indexed
+    pub preprocess: Preprocess,
+    /// Compute a [`LineDescriptor`] for each feature
+    pub descriptors: bool,
+}
+
+impl MatchOptions {
+    pub(crate) fn pair_options(&self) -> PairOptions {
+        PairOptions {
+            overlap_method: self.overlap_method,
+            direction_mode: self.direction_mode,
+        }
+    }
+}
+
+/// Check that the distance tolerance is positive and the angle
+/// tolerance is between 0 and 90 degrees
+pub fn validate_tolerances(
+    distance_tolerance: f64,
+    angle_tolerance: f64,
+) -> Result<(), AnimeError> {
+    let d = distance_tolerance;
+    if !d.is_finite() || d <= 0.0 {
+        return Err(AnimeError::InvalidTolerance(format!(
+            "`distance_tolerance` must be positive, got {d}"
+        )));
+    }
+    let a = angle_tolerance;
+    if a.is_nan() || a <= 0.0 || a >= 90.0 {
+        return Err(AnimeError::InvalidTolerance(format!(
+            "`angle_tolerance` must be between 0 and 90 degrees, got {a}"
+        )));
+    }
+    Ok(())
+}
+
+/// Geometries that have not been matched yet
+pub struct Unmatched {
+    source: Vec<MultiLineString>,
+    target: Vec<MultiLineString>,
+    feature_tolerances: FeatureTolerances,
+}
+
+/// A finished match
+pub struct Matched {
+    anime: Anime,
+}
+
+/// Configure and run a match
+///
+/// Options are set by name on an `AnimeBuilder<Unmatched>`. Calling
+/// [`AnimeBuilder::find_matches()`] validates them and returns an
+/// `AnimeBuilder<Matched>`, the only state that can interpolate.
+pub struct AnimeBuilder<S = Unmatched> {
+    distance_tolerance: f64,
+    angle_tolerance: f64,
+    options: MatchOptions,
+    state: S,
+}
+
+impl<S> AnimeBuilder<S> {
+    /// The options of the match
+    pub fn options(&self) -> &MatchOptions {
+        &self.options
+    }
+}
+
+impl AnimeBuilder<Unmatched> {
+    /// Start configuring a match of source and target geometries
+    pub fn new(
+        source: impl Iterator<Item = impl Into<MultiLineString>>,
+        target: impl Iterator<Item = impl Into<MultiLineString>>,
+    ) -> Self {
+        Self {
+            distance_tolerance: 10.0,
+            angle_tolerance: 5.0,
+            options: MatchOptions::default(),
+            state: Unmatched {
+                source: source.map(Into::into).collect(),
+                target: target.map(Into::into).collect(),
+                feature_tolerances: FeatureTolerances::default(),
+            },
+        }
+    }
+
+    /// Replace all options other than the tolerances
+    pub fn with_options(mut self, options: MatchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Maximum distance between two lines to be considered a match
+    pub fn distance_tolerance(mut self, distance_tolerance: f64) -> Self {
+        self.distance_tolerance = distance_tolerance;
+        self
+    }
+
+    /// Maximum angle in degrees between two lines to be considered a match
+    pub fn angle_tolerance(mut self, angle_tolerance: f64) -> Self {
+        self.angle_tolerance = angle_tolerance;
+        self
+    }
+
+    /// How the shared length of two lines is measured
+    pub fn overlap_method(mut self, overlap_method: OverlapMethod) -> Self {
+        self.options.overlap_method = overlap_method;
+        self
+    }
+
+    /// Whether the geometries are projected or in longitude and latitude
+    pub fn crs_mode(mut self, crs_mode: CrsMode) -> Self {
+        self.options.crs_mode = crs_mode;
+        self
+    }
+
+    /// Whether lines must point the same way to match
+    pub fn direction_mode(mut self, direction_mode: DirectionMode) -> Self {
+        self.options.direction_mode = direction_mode;
+        self
+    }
+
+    /// Number of threads used to find matches
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.options.parallelism = parallelism;
+        self
+    }
+
+    /// Override the distance and angle tolerances per feature. See [`FeatureTolerances`].
+    pub fn feature_tolerances(mut self, feature_tolerances: FeatureTolerances) -> Self {
+        self.state.feature_tolerances = feature_tolerances;
+        self
+    }
+
+    /// Densify or simplify the geometries before they are indexed
+    ///
+    /// Feature lengths are measured before preprocessing, so shared lengths
+    /// are reported against the original features.
+    pub fn preprocess(mut self, preprocess: Preprocess) -> Self {
+        self.options.preprocess = preprocess;
+        self
+    }
+
+    /// Compute a [`LineDescriptor`] for each source and target feature
+    ///
+    /// The descriptors are stored on the [`Anime`]. They are computed along
+    /// with the matches, so they can't filter them. To use descriptors as a
+    /// constraint, load the geometries with
+    /// [`Anime::load_geometries_with_descriptors()`] and pass
+    /// [`crate::descriptor::DescriptorSimilarity::from_anime()`] to
+    /// [`Anime::find_matches_with()`].
+    pub fn descriptors(mut self, descriptors: bool) -> Self {
+        self.options.descriptors = descriptors;
+        self
+    }
+
+    /// Validate the options, load the geometries and find matches
+    pub fn find_matches(self) -> Result<AnimeBuilder<Matched>, AnimeError> {
+        let options = self.options;
+        let distance_tolerance = self.distance_tolerance;
+        let angle_tolerance = self.angle_tolerance;
+        validate_tolerances(distance_tolerance, angle_tolerance)?;
+
+        let Unmatched {
+            mut source,
+            mut target,
+            feature_tolerances,
+        } = self.state;
+        feature_tolerances.validate(source.len(), target.len())?;
+        if options.crs_mode == CrsMode::Geographic {
+            (source, target) = project_equirectangular(source, target);
+        }
+
+        let descriptors = |lines: &[MultiLineString]| {
+            options.descriptors.then(|| {
+                lines
+                    .iter()
+                    .map(LineDescriptor::from_multi_line_string)
+                    .collect()
+            })
+        };
+        let (source_descriptors, target_descriptors) = (descriptors(&source), descriptors(&target));
+
+        // lengths are measured before preprocessing and the lengths recorded
+        // by the trees are discarded
+        let lens = |lines: &[MultiLineString]| {
+            lines
+                .iter()
+                .map(|l| l.length::<Euclidean>())
+                .collect::<Vec<_>>()
+        };
+        let (source_lens, target_lens) = (lens(&source), lens(&target));
+        if options.preprocess != Preprocess::None {
+            let preprocess = |lines: Vec<MultiLineString>| {
+                lines
+                    .into_iter()
+                    .map(|l| options.preprocess.apply_lines(l))
+                    .collect::<Vec<_>>()
+            };
+            (source, target) = (preprocess(source), preprocess(target));
+        }
+
+        let padding = feature_tolerances.padding_fn(distance_tolerance);
+        let (source_tree, target_tree, matches) = match options.parallelism {
+            Parallelism::Sequential => {
+                let source_tree = create_source_rtree(source.into_iter(), &mut Vec::new());
+                let target_tree = create_target_rtree(target.into_iter(), &mut Vec::new(), padding);
+                let matches = find_candidate_matches_with(
+                    &source_tree,
+                    &target_tree,
+                    angle_tolerance,
+                    distance_tolerance,
+                    &feature_tolerances,
+                    None,
+                    options.pair_options(),
+                );
+                (source_tree, target_tree, matches)
+            }
+            Parallelism::Threads(n) => {
+                let (source_tree, target_tree) = thread::scope(|s| {
+                    let source_tree =
+                        s.spawn(|| create_source_rtree(source.into_iter(), &mut Vec::new()));
+                    let target_tree =
+                        create_target_rtree(target.into_iter(), &mut Vec::new(), padding);
+                    (source_tree.join().unwrap(), target_tree)
+                });
+                let matches = find_candidate_matches_threaded(
+                    &source_tree,
+                    &target_tree,
+                    (distance_tolerance, angle_tolerance),
+                    &feature_tolerances,
+                    options.pair_options(),
+                    n.get(),
+                );
+                (source_tree, target_tree, matches)
+            }
+        };
+
+        let mut anime = Anime::from_trees(
+            distance_tolerance,
+            angle_tolerance,
+            feature_tolerances,
+            options,
+            (source_tree, source_lens),
+            (target_tree, target_lens),
+        );
+        anime.source_descriptors = source_descriptors;
+        anime.target_descriptors = target_descriptors;
+        anime.matches = OnceLock::from(matches);
+        Ok(AnimeBuilder {
+            distance_tolerance,
+            angle_tolerance,
+            options,
+            state: Matched { anime },
+        })
+    }
+}
+
+impl AnimeBuilder<Matched> {
+    /// The matched [`Anime`]
+    pub fn anime(&self) -> &Anime {
+        &self.state.anime
+    }
+
+    /// The matched [`Anime`], which can be used with the rest of the crate
+    pub fn into_anime(self) -> Anime {
+        self.state.anime
+    }
+
+    /// Drop the R* Trees and keep what is needed to interpolate
+    pub fn into_model(self) -> MatchModel {
+        MatchModel::try_from(self.state.anime).expect("matches are set in the `Matched` state")
+    }
+
+    /// Perform numeric attribute interpolation. See [`Anime::interpolate()`].
+    pub fn interpolate(
+        &self,
+        var: &[f64],
+        tensive: Tensive,
+    ) -> Result<Vec<InterpolatedValue>, AnimeError> {
+        self.state.anime.interpolate(var, tensive)
+    }
+
+    /// Extensive interpolation. See [`Anime::interpolate_extensive()`].
+    pub fn interpolate_extensive(
+        &self,
+        source_var: &[f64],
+    ) -> Result<Vec<InterpolatedValue>, AnimeError> {
+        self.state.anime.interpolate_extensive(source_var)
+    }
+
+    /// Intensive interpolation. See [`Anime::interpolate_intensive()`].
+    pub fn interpolate_intensive(
+        &self,
+        source_var: &[f64],
+    ) -> Result<Vec<InterpolatedValue>, AnimeError> {
+        self.state.anime.interpolate_intensive(source_var)
+    }
+
+    /// The matches as a flat table. See [`Anime::match_table()`].
+    pub fn match_table(&self) -> Vec<MatchRow> {
+        // the matches are always set in the `Matched` state
+        self.state.anime.match_table().unwrap_or_default()
+    }
+}
+
+/// Find candidate matches by splitting the target component lines between threads
+fn find_candidate_matches_threaded(
+    source_tree: &SourceTree,
+    target_tree: &TargetTree,
+    (distance_tolerance, angle_tolerance): (f64, f64),
+    feature_tolerances: &FeatureTolerances,
+    pair_options: PairOptions,
+    n_threads: usize,
+) -> MatchesMap {
+    let targets = target_tree.iter().collect::<Vec<_>>();
+    let chunk_size = targets.len().div_ceil(n_threads).max(1);
+
+    let found = thread::scope(|s| {
+        let handles = targets
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    let mut found = Vec::new();
+                    for cy in chunk {
+                        let (j, y_slope) = cy.data;
+                        for cx in source_tree.locate_in_envelope_intersecting(&cy.envelope()) {
+                            let (i, x_slope) = cx.data;
+                            let shared_len = pair_shared_len(
+                                cx.geom(),
+                                x_slope,
+                                cy.geom(),
+                                y_slope,
+                                feature_tolerances.angle(i, j, angle_tolerance),
+                                feature_tolerances.distance(i, j, distance_tolerance),
+                                pair_options,
+                            );
+                            if let Some(shared_len) = shared_len {
+                                found.push((i, j, shared_len));
+                            }
+                        }
+                    }
+                    found
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut matches = MatchesMap::new();
+    for (i, j, shared_len) in found {
+        add_candidate(&mut matches, i, j, shared_len, 1.0);
+    }
+    matches
+}
+
+/// Project longitude and latitude to meters around the center of all geometries
+fn project_equirectangular(
+    source: Vec<MultiLineString>,
+    target: Vec<MultiLineString>,
+) -> (Vec<MultiLineString>, Vec<MultiLineString>) {
+    let bbox = source
+        .iter()
+        .chain(target.iter())
+        .filter_map(|g| g.bounding_rect())
+        .reduce(|a, b| {
+            Rect::new(
+                Coord {
+                    x: a.min().x.min(b.min().x),
+                    y: a.min().y.min(b.min().y),
+                },
+                Coord {
+                    x: a.max().x.max(b.max().x),
+                    y: a.max().y.max(b.max().y),
+                },
+            )
+        });
+    let lat0 = bbox.map_or(0.0, |b| b.center().y).to_radians();
+    let project = |g: MultiLineString| {
+        g.map_coords(|c| Coord {
+            x: c.x.to_radians() * lat0.cos() * EARTH_RADIUS,
+            y: c.y.to_radians() * EARTH_RADIUS,
+        })
+    };
+    (
+        source.into_iter().map(project).collect(),
+        target.into_iter().map(project).collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+
+    fn lines() -> (Vec<geo_types::LineString>, Vec<geo_types::LineString>) {
+        let source = vec![
+            line_string![(x: 0.0, y: 0.0), (x: 100.0, y: 0.0)],
+            line_string![(x: 0.0, y: 50.0), (x: 100.0, y: 50.0)],
+        ];
+        let target = vec![
+            line_string![(x: 0.0, y: 1.0), (x: 100.0, y: 1.0)],
+            line_string![(x: 0.0, y: 52.0), (x: 100.0, y: 52.0)],
+        ];
+        (source, target)
+    }
+
+    #[test]
+    fn builder_options_reach_the_anime() {
+        let (source, target) = lines();
+        let feature_tolerances = FeatureTolerances {
+            // too small for the second target, which is 2 away
+            target_distance: Some(vec![5.0, 1.0]),
+            ..Default::default()
+        };
+        let build = |parallelism| {
+            AnimeBuilder::new(source.iter().cloned(), target.iter().cloned())
+                .distance_tolerance(5.0)
+                .feature_tolerances(feature_tolerances.clone())
+                .preprocess(Preprocess::Densify(10.0))
+                .descriptors(true)
+                .parallelism(parallelism)
+                .find_matches()
+                .unwrap()
+                .into_anime()
+        };
+
+        let anime = build(Parallelism::Sequential);
+        assert_eq!(anime.source_lens, vec![100.0, 100.0]);
+        assert_eq!(anime.source_tree.size(), 20);
+        assert!(anime.source_descriptors.is_some() && anime.target_descriptors.is_some());
+        let matches = anime.matches.get().unwrap();
+        assert_eq!(matches.keys().copied().collect::<Vec<_>>(), vec![0]);
+        assert!((matches[&0][0].shared_len - 100.0).abs() < 1e-9);
+
+        let threaded = build(Parallelism::Threads(NonZeroUsize::new(2).unwrap()));
+        let threaded = threaded.matches.get().unwrap();
+        assert_eq!(threaded.keys().copied().collect::<Vec<_>>(), vec![0]);
+        assert!((threaded[&0][0].shared_len - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn geographic_matches_refuse_projected_operations() {
+        let (source, target) = lines();
+        let degrees = |lines: Vec<geo_types::LineString>| {
+            lines
+                .into_iter()
+                .map(|l| MultiLineString::new(vec![l.map_coords(|c| c / 1e5)]))
+                .collect::<Vec<_>>()
+        };
+        let (source, target) = (degrees(source), degrees(target));
+        let anime = AnimeBuilder::new(source.iter().cloned(), target.iter().cloned())
+            .crs_mode(CrsMode::Geographic)
+            .find_matches()
+            .unwrap()
+            .into_anime();
+        assert_eq!(anime.options.crs_mode, CrsMode::Geographic);
+        assert!(matches!(
+            anime.conflate(&source, &target),
+            Err(AnimeError::InvalidOption(_))
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{Anime, AnimeError, MatchesMap};
+use std::collections::BTreeMap;
+
+/// Cardinality of a group of matched features
+///
+/// The first term refers to the source features and the second to
+/// the target features. For example, [`Cardinality::OneToMany`] is
+/// a single source feature matched to multiple target features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cardinality {
+    OneToOne,
+    OneToMany,
+    ManyToOne,
+    ManyToMany,
+}
+
+impl Cardinality {
+    fn from_counts(n_source: usize, n_target: usize) -> Self {
+        match (n_source > 1, n_target > 1) {
+            (false, false) => Cardinality::OneToOne,
+            (false, true) => Cardinality::OneToMany,
+            (true, false) => Cardinality::ManyToOne,
+            (true, true) => Cardinality::ManyToMany,
+        }
+    }
+}
+
+impl std::fmt::Display for Cardinality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Cardinality::OneToOne => "1:1",
+            Cardinality::OneToMany => "1:n",
+            Cardinality::ManyToOne => "n:1",
+            Cardinality::ManyToMany => "n:m",
+        };
+        write!(f, "{label}")
+    }
+}
+
+/// A connected component of the bipartite match graph
+///
+/// Every source in `source_ids` is matched, directly or transitively,
+/// to every target in `target_ids`. Both vectors are sorted.
+#[derive(Debug, Clone)]
+pub struct MatchComponent {
+    pub cardinality: Cardinality,
+    pub source_ids: Vec<usize>,
+    pub target_ids: Vec<usize>,
+}
+
+/// Disjoint set forest with path halving and union by size
+#[derive(Debug, Clone)]
+pub(crate) struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    pub(crate) fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return;
+        }
+        let (big, small) = if self.size[ra] >= self.size[rb] {
+            (ra, rb)
+        } else {
+            (rb, ra)
+        };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+    }
+}
+
+/// Find the connected components of the bipartite match graph
+///
+/// Sources and targets are nodes and each [`crate::MatchCandidate`] with
+/// a positive shared length is an edge. Candidates that only touch end
+/// to end are ignored so that a connected network does not collapse into
+/// a single component. Features without any such match are not part of
+/// a component. Components are ordered by their smallest target index.
+pub fn match_components(matches: &MatchesMap) -> Vec<MatchComponent> {
+    let edges = || {
+        matches.iter().flat_map(|(j, cands)| {
+            cands
+                .iter()
+                .filter(|ci| ci.shared_len > 0.0)
+                .map(move |ci| (*j, ci.source_index))
+        })
+    };
+
+    // assign dense node ids: sources first, then targets
+    let mut source_nodes: BTreeMap<usize, usize> = BTreeMap::new();
+    for (_, i) in edges() {
+        let n = source_nodes.len();
+        source_nodes.entry(i).or_insert(n);
+    }
+    let n_sources = source_nodes.len();
+    let mut target_nodes: BTreeMap<usize, usize> = BTreeMap::new();
+    for (j, _) in edges() {
+        let n = n_sources + target_nodes.len();
+        target_nodes.entry(j).or_insert(n);
+    }
+
+    let mut uf = UnionFind::new(n_sources + target_nodes.len());
+    for (j, i) in edges() {
+        uf.union(target_nodes[&j], source_nodes[&i]);
+    }
+
+    // group by root, keyed by the first target encountered so that
+    // the output is ordered by the smallest target index
+    let mut groups: BTreeMap<usize, (Vec<usize>, Vec<usize>)> = BTreeMap::new();
+    let mut root_key: BTreeMap<usize, usize> = BTreeMap::new();
+    for (j, tnode) in target_nodes.iter() {
+        let root = uf.find(*tnode);
+        let key = *root_key.entry(root).or_insert(*j);
+        groups.entry(key).or_default().1.push(*j);
+    }
+    for (i, snode) in source_nodes.iter() {
+        let root = uf.find(*snode);
+        let key = root_key[&root];
+        groups.entry(key).or_default().0.push(*i);
+    }
+
+    groups
+        .into_values()
+        .map(|(source_ids, target_ids)| MatchComponent {
+            cardinality: Cardinality::from_counts(source_ids.len(), target_ids.len()),
+            source_ids,
+            target_ids,
+        })
+        .collect()
+}
+
+impl Anime {
+    /// Classify matches by cardinality
+    ///
+    /// Returns the connected components of the bipartite match graph.
+    /// Each component is labelled 1:1, 1:n, n:1, or n:m. See [`match_components()`].
+    pub fn match_components(&self) -> Result<Vec<MatchComponent>, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        Ok(match_components(matches))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{candidate, chain};
+
+    /// One component of each cardinality
+    fn matches() -> MatchesMap {
+        MatchesMap::from([
+            (0, vec![candidate(0, 10.0)]),
+            (1, vec![candidate(1, 4.0), candidate(2, 6.0)]),
+            (2, vec![candidate(3, 5.0)]),
+            (3, vec![candidate(3, 5.0)]),
+            (4, vec![candidate(4, 3.0), candidate(5, 2.0)]),
+            (5, vec![candidate(5, 8.0)]),
+        ])
+    }
+
+    #[test]
+    fn components_are_classified_by_cardinality() {
+        let components = match_components(&matches());
+        let summary = components
+            .iter()
+            .map(|c| {
+                (
+                    c.cardinality,
+                    c.source_ids.as_slice(),
+                    c.target_ids.as_slice(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (Cardinality::OneToOne, &[0][..], &[0][..]),
+                (Cardinality::ManyToOne, &[1, 2][..], &[1][..]),
+                (Cardinality::OneToMany, &[3][..], &[2, 3][..]),
+                (Cardinality::ManyToMany, &[4, 5][..], &[4, 5][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn touching_segments_do_not_join_components() {
+        let chain = chain(3, 0.0);
+        let anime = Anime::new(chain.iter().cloned(), chain.iter().cloned(), 2.0, 5.0);
+        // neighbouring segments touch end to end
+        let matches = anime.matches.get().unwrap();
+        assert!(matches[&1].iter().any(|ci| ci.shared_len == 0.0));
+
+        let components = anime.match_components().unwrap();
+        assert_eq!(components.len(), 3);
+        for (k, c) in components.iter().enumerate() {
+            assert_eq!(c.cardinality, Cardinality::OneToOne);
+            assert_eq!(
+                (c.source_ids.as_slice(), c.target_ids.as_slice()),
+                (&[k][..], &[k][..])
+            );
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{
+    cardinality::{match_components, Cardinality, MatchComponent},
+    coverage::coverage_report,
+    Anime, AnimeError, MatchesMap,
+};
+
+/// Changes between two versions of the same network
+///
+/// The older version is the source and the newer version is the target.
+/// Pairs are stored as `(old_index, new_index)`.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeSet {
+    /// 1:1 matches where both features are covered at or above the threshold
+    pub unchanged: Vec<(usize, usize)>,
+    /// 1:1 matches where either feature is covered below the threshold
+    pub modified: Vec<(usize, usize)>,
+    /// One old feature matched to many new features
+    pub split: Vec<MatchComponent>,
+    /// Many old features matched to one new feature
+    pub merged: Vec<MatchComponent>,
+    /// Many old features matched to many new features
+    pub restructured: Vec<MatchComponent>,
+    /// New features without a match in the old version
+    pub added: Vec<usize>,
+    /// Old features without a match in the new version
+    pub removed: Vec<usize>,
+}
+
+/// Classify changes from matches between an old and a new network
+///
+/// `unchanged_threshold` is the minimum mutual coverage, as a fraction of
+/// feature length, for a 1:1 match to be considered unchanged.
+pub fn change_set(
+    matches: &MatchesMap,
+    old_lens: &[f64],
+    new_lens: &[f64],
+    unchanged_threshold: f64,
+) -> ChangeSet {
+    let coverage = coverage_report(matches, old_lens, new_lens, unchanged_threshold);
+    let mut res = ChangeSet {
+        added: coverage.unmatched_targets,
+        removed: coverage.unmatched_sources,
+        ..Default::default()
+    };
+
+    for component in match_components(matches) {
+        match component.cardinality {
+            Cardinality::OneToOne => {
+                let (i, j) = (component.source_ids[0], component.target_ids[0]);
+                let is_unchanged = coverage.source_coverage[i] >= unchanged_threshold
+                    && coverage.target_coverage[j] >= unchanged_threshold;
+                if is_unchanged {
+                    res.unchanged.push((i, j));
+                } else {
+                    res.modified.push((i, j));
+                }
+            }
+            Cardinality::OneToMany => res.split.push(component),
+            Cardinality::ManyToOne => res.merged.push(component),
+            Cardinality::ManyToMany => res.restructured.push(component),
+        }
+    }
+    res
+}
+
+/// Detect changes between two versions of the same network
+///
+/// Matches `old` against `new` and classifies the result with [`change_set()`].
+pub fn diff_networks(
+    old: impl Iterator<Item = geo_types::LineString>,
+    new: impl Iterator<Item = geo_types::LineString>,
+    distance_tolerance: f64,
+    angle_tolerance: f64,
+    unchanged_threshold: f64,
+) -> ChangeSet {
+    let anime = Anime::new(old, new, distance_tolerance, angle_tolerance);
+    let matches = anime.matches.get().unwrap();
+    change_set(
+        matches,
+        &anime.source_lens,
+        &anime.target_lens,
+        unchanged_threshold,
+    )
+}
+
+impl Anime {
+    /// Classify matches as changes between two versions of a network
+    ///
+    /// The source is treated as the old version and the target as the new one.
+    /// See [`change_set()`].
+    pub fn change_set(&self, unchanged_threshold: f64) -> Result<ChangeSet, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        Ok(change_set(
+            matches,
+            &self.source_lens,
+            &self.target_lens,
+            unchanged_threshold,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain;
+    use geo_types::{line_string, LineString};
+
+    fn segment(x0: f64, x1: f64, y: f64) -> LineString {
+        line_string![(x: x0, y: y), (x: x1, y: y)]
+    }
+
+    #[test]
+    fn changes_are_classified_by_category() {
+        let mut old = chain(3, 0.0);
+        old.extend([
+            segment(0.0, 10.0, 100.0),
+            segment(0.0, 20.0, 200.0),
+            segment(0.0, 10.0, 300.0),
+            segment(10.0, 20.0, 300.0),
+            segment(0.0, 10.0, 500.0),
+        ]);
+        let mut new = chain(3, 0.0);
+        new.extend([
+            segment(0.0, 6.0, 100.0),
+            segment(0.0, 10.0, 200.0),
+            segment(10.0, 20.0, 200.0),
+            segment(0.0, 20.0, 300.0),
+            segment(0.0, 10.0, 400.0),
+        ]);
+
+        let changes = diff_networks(old.into_iter(), new.into_iter(), 2.0, 5.0, 0.9);
+        let ids = |c: &[MatchComponent]| {
+            c.iter()
+                .map(|c| (c.source_ids.clone(), c.target_ids.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changes.unchanged, vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(changes.modified, vec![(3, 3)]);
+        assert_eq!(ids(&changes.split), vec![(vec![4], vec![4, 5])]);
+        assert_eq!(ids(&changes.merged), vec![(vec![5, 6], vec![6])]);
+        assert!(changes.restructured.is_empty());
+        assert_eq!(changes.added, vec![7]);
+        assert_eq!(changes.removed, vec![7]);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    builder::DirectionMode,
+    linear::{line_substring, locate_point, project},
+    overlap::same_direction,
+    Anime, AnimeError,
+};
+use geo::{Distance, Euclidean};
+use geo_types::{Coord, Line, LineString, MultiLineString, Point};
+use rstar::RTreeObject;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Stretch of a source feature that is either snapped to a target or kept
+struct Run {
+    // target feature and the index of its component `LineString`
+    target: Option<(usize, usize)>,
+    coords: Vec<Coord>,
+}
+
+/// Overlap intervals this close to the end of a component line are extended to it
+const SPLIT_EPS: f64 = 1e-9;
+
+impl Anime {
+    /// Snap matched stretches of the source geometries onto the target geometries
+    ///
+    /// Each component line of a matched source feature is assigned to the
+    /// matched target feature it overlaps the most, using the same angle and
+    /// distance tests as [`Anime::find_matches()`]. Component lines are split
+    /// at the ends of the interval overlapped by that target. Consecutive
+    /// overlapped stretches with the same target are replaced by the portion of
+    /// the target between the projections of the stretch's start and end.
+    /// Stretches without an overlap are kept and connected directly to the
+    /// adjacent target portions.
+    ///
+    /// Each component `LineString` of a multi-part source feature is
+    /// conflated on its own. Stretches are snapped to the component of the
+    /// target feature closest to them.
+    ///
+    /// `source` and `target` must be the geometries used to create `self`.
+    /// Returns one `MultiLineString` per source feature. Matches found with
+    /// [`crate::builder::CrsMode::Geographic`] can't be conflated.
+    pub fn conflate(
+        &self,
+        source: &[MultiLineString],
+        target: &[MultiLineString],
+    ) -> Result<Vec<MultiLineString>, AnimeError> {
+        if source.len() != self.source_lens.len() || target.len() != self.target_lens.len() {
+            return Err(AnimeError::IncorrectGeometryLength);
+        }
+        self.require_projected("conflate")?;
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+
+        // invert the matches to find the targets of each source
+        let mut source_targets: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for (j, cands) in matches.iter() {
+            for ci in cands.iter().filter(|ci| ci.shared_len > 0.0) {
+                source_targets
+                    .entry(ci.source_index)
+                    .or_default()
+                    .insert(*j);
+            }
+        }
+
+        let res = source
+            .iter()
+            .enumerate()
+            .map(|(i, si)| match source_targets.get(&i) {
+                Some(targets) => si
+                    .iter()
+                    .map(|sk| self.conflate_one(i, sk, targets, target))
+                    .collect(),
+                None => si.clone(),
+            })
+            .collect();
+        Ok(res)
+    }
+
+    /// Matched target of each component line of source `i` and the interval
+    /// of the component line that it overlaps, as fractions of its length
+    fn assign_lines(
+        &self,
+        i: usize,
+        si: &LineString,
+        targets: &BTreeSet<usize>,
+    ) -> Vec<Option<(usize, f64, f64)>> {
+        si.lines()
+            .map(|li| {
+                let x_deg = li.slope().atan().to_degrees();
+                let env = li.envelope();
+                self.target_tree
+                    .locate_in_envelope_intersecting(&env)
+                    .filter(|cy| targets.contains(&cy.data.0))
+                    .filter_map(|cy| {
+                        let (j, y_slope) = cy.data;
+                        let angle_tolerance =
+                            self.feature_tolerances.angle(i, j, self.angle_tolerance);
+                        let distance_tolerance =
+                            self.feature_tolerances
+                                .distance(i, j, self.distance_tolerance);
+                        let y_deg = y_slope.atan().to_degrees();
+                        let tar = cy.geom();
+                        let is_match = (x_deg - y_deg).abs() < angle_tolerance
+                            && tar.distance(&li) <= distance_tolerance
+                            && (self.options.direction_mode == DirectionMode::Undirected
+                                || same_direction(&li, &tar.0));
+                        is_match.then(|| (j, overlap_interval(&li, &tar.0)))
+                    })
+                    .fold(
+                        BTreeMap::<usize, (f64, f64, f64)>::new(),
+                        |mut acc, (j, (lo, hi))| {
+                            let e = acc.entry(j).or_insert((0.0, lo, hi));
+                            *e = (e.0 + hi - lo, e.1.min(lo), e.2.max(hi));
+                            acc
+                        },
+                    )
+                    .into_iter()
+                    .filter(|(_, (len, _, _))| *len > 0.0)
+                    .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+                    .map(|(j, (_, lo, hi))| {
+                        let lo = if lo <= SPLIT_EPS { 0.0 } else { lo };
+                        let hi = if hi >= 1.0 - SPLIT_EPS { 1.0 } else { hi };
+                        (j, lo, hi)
+                    })
+            })
+            .collect()
+    }
+
+    fn conflate_one(
+        &self,
+        i: usize,
+        si: &LineString,
+        targets: &BTreeSet<usize>,
+        target: &[MultiLineString],
+    ) -> LineString {
+        let assigned = self.assign_lines(i, si, targets);
+
+        let mut runs: Vec<Run> = Vec::new();
+        let mut push = |j: Option<usize>, start: Coord, end: Coord| {
+            let target = j.map(|j| (j, closest_component(&target[j], start, end)));
+            match runs.last_mut() {
+                Some(run) if run.target == target => run.coords.push(end),
+                _ => runs.push(Run {
+                    target,
+                    coords: vec![start, end],
+                }),
+            }
+        };
+        for (li, a) in si.lines().zip(assigned) {
+            match a {
+                Some((j, lo, hi)) => {
+                    let at = |t: f64| li.start + li.delta() * t;
+                    if lo > 0.0 {
+                        push(None, li.start, at(lo));
+                    }
+                    push(Some(j), at(lo), at(hi));
+                    if hi < 1.0 {
+                        push(None, at(hi), li.end);
+                    }
+                }
+                None => push(None, li.start, li.end),
+            }
+        }
+
+        let mut coords: Vec<Coord> = Vec::with_capacity(si.0.len());
+        let n_runs = runs.len();
+        for (r, run) in runs.iter().enumerate() {
+            match run.target {
+                Some((j, k)) => {
+                    let tj = &target[j].0[k];
+                    let from = locate_point(tj, run.coords[0]);
+                    let to = locate_point(tj, run.coords[run.coords.len() - 1]);
+                    coords.extend(line_substring(tj, from, to));
+                }
+                None => {
+                    // shared end vertices are replaced by the adjacent target portion
+                    let first = usize::from(r > 0);
+                    let last = run.coords.len() - usize::from(r + 1 < n_runs);
+                    coords.extend(run.coords[first..last].iter().copied());
+                }
+            }
+        }
+        coords.dedup();
+
+        if coords.len() < 2 {
+            si.clone()
+        } else {
+            LineString::new(coords)
+        }
+    }
+}
+
+/// Index of the component of `line` closest to the midpoint of `start` and `end`
+fn closest_component(line: &MultiLineString, start: Coord, end: Coord) -> usize {
+    let mid = Point::from((start + end) / 2.0);
+    line.iter()
+        .map(|l| Euclidean::distance(&mid, l))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(k, _)| k)
+}
+
+/// Interval of `l` overlapped by the projection of `other`, as fractions of its length
+fn overlap_interval(l: &Line, other: &Line) -> (f64, f64) {
+    let t0 = project(l, other.start);
+    let t1 = project(l, other.end);
+    (t0.min(t1), t0.max(t1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+
+    #[test]
+    fn conflate_keeps_unmatched_source_geometry() {
+        let source = vec![MultiLineString::new(vec![line_string![
+            (x: 0.0, y: 0.0),
+            (x: 10.0, y: 0.0),
+            (x: 20.0, y: 0.0),
+            (x: 30.0, y: 10.0),
+        ]])];
+        let target = vec![MultiLineString::new(vec![line_string![
+            (x: 2.0, y: 1.0),
+            (x: 18.0, y: 1.0),
+        ]])];
+        let anime = Anime::new(source.iter().cloned(), target.iter().cloned(), 2.0, 5.0);
+
+        let res = anime.conflate(&source, &target).unwrap();
+        let expected = line_string![
+            (x: 0.0, y: 0.0),
+            (x: 2.0, y: 1.0),
+            (x: 18.0, y: 1.0),
+            (x: 20.0, y: 0.0),
+            (x: 30.0, y: 10.0),
+        ];
+        assert_eq!(res, vec![MultiLineString::new(vec![expected])]);
+    }
+}
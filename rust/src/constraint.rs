@@ -0,0 +1,120 @@
+use crate::{find_candidate_matches_with, Anime, AnimeError};
+use std::collections::HashMap;
+
+/// Attribute based compatibility between a source and target feature
+///
+/// Constraints are evaluated once per `(source, target)` pair before the
+/// distance and overlap computations. A weight of `0.0` excludes the pair
+/// entirely. A weight between `0.0` and `1.0` keeps the pair but lowers its
+/// [`crate::MatchCandidate::confidence`].
+pub trait MatchConstraint {
+    /// Compatibility weight in the range `[0, 1]`
+    fn weight(&self, source_index: usize, target_index: usize) -> f64;
+}
+
+/// Any predicate over source and target indices is a constraint
+///
+/// Pairs for which the predicate returns `false` are excluded.
+impl<F> MatchConstraint for F
+where
+    F: Fn(usize, usize) -> bool,
+{
+    fn weight(&self, source_index: usize, target_index: usize) -> f64 {
+        if self(source_index, target_index) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Two constraints are combined by multiplying their weights
+impl<A: MatchConstraint, B: MatchConstraint> MatchConstraint for (A, B) {
+    fn weight(&self, source_index: usize, target_index: usize) -> f64 {
+        let w = self.0.weight(source_index, target_index);
+        if w <= 0.0 {
+            return 0.0;
+        }
+        w * self.1.weight(source_index, target_index)
+    }
+}
+
+/// Compatibility weights between source and target feature classes
+///
+/// Each feature is assigned a class label such as a road class. The weight of
+/// a pair is looked up from the class of the source and the class of the target.
+/// Class combinations that have not been set use the `default_weight`.
+#[derive(Debug, Clone)]
+pub struct CompatibilityMatrix {
+    source_classes: Vec<String>,
+    target_classes: Vec<String>,
+    weights: HashMap<String, HashMap<String, f64>>,
+    default_weight: f64,
+}
+
+impl CompatibilityMatrix {
+    /// Create a new matrix from the class of each source and target feature
+    pub fn new(
+        source_classes: Vec<String>,
+        target_classes: Vec<String>,
+        default_weight: f64,
+    ) -> Self {
+        Self {
+            source_classes,
+            target_classes,
+            weights: HashMap::new(),
+            default_weight,
+        }
+    }
+
+    /// Set the weight between a source class and a target class
+    pub fn set(mut self, source_class: &str, target_class: &str, weight: f64) -> Self {
+        self.weights
+            .entry(source_class.to_string())
+            .or_default()
+            .insert(target_class.to_string(), weight);
+        self
+    }
+}
+
+impl MatchConstraint for CompatibilityMatrix {
+    fn weight(&self, source_index: usize, target_index: usize) -> f64 {
+        let (Some(sc), Some(tc)) = (
+            self.source_classes.get(source_index),
+            self.target_classes.get(target_index),
+        ) else {
+            return self.default_weight;
+        };
+
+        self.weights
+            .get(sc)
+            .and_then(|w| w.get(tc))
+            .copied()
+            .unwrap_or(self.default_weight)
+    }
+}
+
+impl Anime {
+    /// Find candidate matches that satisfy an attribute constraint
+    ///
+    /// Like [`Anime::find_matches()`] but source and target pairs are first
+    /// checked with the `constraint`. See [`MatchConstraint`].
+    pub fn find_matches_with(
+        &mut self,
+        constraint: &dyn MatchConstraint,
+    ) -> Result<&mut Anime, AnimeError> {
+        let matches = find_candidate_matches_with(
+            &self.source_tree,
+            &self.target_tree,
+            self.angle_tolerance,
+            self.distance_tolerance,
+            &self.feature_tolerances,
+            Some(constraint),
+            self.options.pair_options(),
+        );
+        self.matches
+            .set(matches)
+            .map_err(AnimeError::AlreadyMatched)?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{Anime, AnimeError, MatchesMap};
+
+/// Summary of how much of each feature was matched
+///
+/// Coverage is the total shared length of a feature divided by its
+/// length, capped at `1.0`. Features with zero length have a coverage of `0.0`.
+#[derive(Debug, Clone)]
+pub struct CoverageReport {
+    /// Index of source features without any match of positive length
+    pub unmatched_sources: Vec<usize>,
+    /// Index of target features without any match of positive length
+    pub unmatched_targets: Vec<usize>,
+    /// Matched fraction of each source feature's length
+    pub source_coverage: Vec<f64>,
+    /// Matched fraction of each target feature's length
+    pub target_coverage: Vec<f64>,
+    /// Index of source features whose coverage is below the threshold
+    pub under_matched_sources: Vec<usize>,
+    /// Index of target features whose coverage is below the threshold
+    pub under_matched_targets: Vec<usize>,
+    /// The threshold used to determine under-matched features
+    pub threshold: f64,
+}
+
+/// Total shared length per source and per target feature
+pub(crate) fn shared_totals(
+    matches: &MatchesMap,
+    n_source: usize,
+    n_target: usize,
+) -> (Vec<f64>, Vec<f64>) {
+    let mut source_shared = vec![0.0; n_source];
+    let mut target_shared = vec![0.0; n_target];
+    for (j, cands) in matches.iter() {
+        for ci in cands {
+            source_shared[ci.source_index] += ci.shared_len;
+            target_shared[*j] += ci.shared_len;
+        }
+    }
+    (source_shared, target_shared)
+}
+
+fn coverage_fraction(shared: f64, len: f64) -> f64 {
+    if len > 0.0 {
+        (shared / len).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Build a [`CoverageReport`] from matches and feature lengths
+///
+/// Features whose coverage is strictly less than `threshold` are
+/// reported as under-matched. Unmatched features are always under-matched
+/// when `threshold` is positive. Candidates with a shared length of zero,
+/// such as segments that only touch end to end, do not count as a match.
+pub fn coverage_report(
+    matches: &MatchesMap,
+    source_lens: &[f64],
+    target_lens: &[f64],
+    threshold: f64,
+) -> CoverageReport {
+    let (source_shared, target_shared) =
+        shared_totals(matches, source_lens.len(), target_lens.len());
+
+    let mut source_matched = vec![false; source_lens.len()];
+    let mut target_matched = vec![false; target_lens.len()];
+    for (j, cands) in matches.iter() {
+        for ci in cands.iter().filter(|ci| ci.shared_len > 0.0) {
+            source_matched[ci.source_index] = true;
+            target_matched[*j] = true;
+        }
+    }
+
+    let unmatched_sources = (0..source_lens.len())
+        .filter(|i| !source_matched[*i])
+        .collect();
+    let unmatched_targets = (0..target_lens.len())
+        .filter(|j| !target_matched[*j])
+        .collect();
+
+    let source_coverage = source_shared
+        .iter()
+        .zip(source_lens)
+        .map(|(s, l)| coverage_fraction(*s, *l))
+        .collect::<Vec<_>>();
+    let target_coverage = target_shared
+        .iter()
+        .zip(target_lens)
+        .map(|(s, l)| coverage_fraction(*s, *l))
+        .collect::<Vec<_>>();
+
+    let under = |cov: &[f64]| {
+        cov.iter()
+            .enumerate()
+            .filter(|(_, c)| **c < threshold)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>()
+    };
+
+    CoverageReport {
+        unmatched_sources,
+        unmatched_targets,
+        under_matched_sources: under(&source_coverage),
+        under_matched_targets: under(&target_coverage),
+        source_coverage,
+        target_coverage,
+        threshold,
+    }
+}
+
+impl Anime {
+    /// Report unmatched and under-matched features
+    ///
+    /// `threshold` is the minimum fraction of a feature's length that must be
+    /// matched for it not to be considered under-matched. See [`coverage_report()`].
+    pub fn coverage_report(&self, threshold: f64) -> Result<CoverageReport, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        Ok(coverage_report(
+            matches,
+            &self.source_lens,
+            &self.target_lens,
+            threshold,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candidate;
+
+    #[test]
+    fn coverage_is_capped_and_reports_unmatched_features() {
+        // source 1 is matched to both targets and source 2 to none
+        let matches = MatchesMap::from([
+            (0, vec![candidate(0, 4.0), candidate(1, 6.0)]),
+            (1, vec![candidate(1, 5.0)]),
+        ]);
+        let source_lens = [8.0, 10.0, 5.0];
+        let target_lens = [10.0, 10.0, 3.0];
+        let report = coverage_report(&matches, &source_lens, &target_lens, 0.9);
+
+        assert_eq!(report.unmatched_sources, vec![2]);
+        assert_eq!(report.unmatched_targets, vec![2]);
+        assert_eq!(report.source_coverage, vec![0.5, 1.0, 0.0]);
+        assert_eq!(report.target_coverage, vec![1.0, 0.5, 0.0]);
+        assert_eq!(report.under_matched_sources, vec![0, 2]);
+        assert_eq!(report.under_matched_targets, vec![1, 2]);
+    }
+
+    #[test]
+    fn touching_features_are_unmatched() {
+        // source 2 only touches target 1 end to end
+        let matches = MatchesMap::from([
+            (0, vec![candidate(0, 10.0)]),
+            (1, vec![candidate(1, 10.0), candidate(2, 0.0)]),
+            (2, vec![candidate(1, 0.0)]),
+        ]);
+        let lens = [10.0, 10.0, 10.0];
+        let report = coverage_report(&matches, &lens, &lens, 0.5);
+
+        assert_eq!(report.unmatched_sources, vec![2]);
+        assert_eq!(report.unmatched_targets, vec![2]);
+    }
+}
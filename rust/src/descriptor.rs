@@ -0,0 +1,236 @@
+use crate::{
+    builder::MatchOptions, constraint::MatchConstraint, create_source_rtree, create_target_rtree,
+    tolerance::FeatureTolerances, Anime,
+};
+use geo::{Distance, Euclidean, Length};
+use geo_types::{LineString, MultiLineString};
+
+/// Number of equal length bins in a curvature signature
+pub const CURVATURE_BINS: usize = 8;
+
+/// Feature level shape descriptors of a `LineString`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineDescriptor {
+    /// Length divided by the straight line distance between the end points
+    ///
+    /// This is `1.0` for a straight line and infinite for a closed line.
+    pub sinuosity: f64,
+    /// Sum of the absolute turning angles at each vertex in degrees
+    pub turning_angle: f64,
+    /// Turning angle per unit length in equal length bins along the line
+    pub curvature: [f64; CURVATURE_BINS],
+}
+
+impl LineDescriptor {
+    /// Compute the descriptors of a `LineString`
+    pub fn new(line: &LineString) -> Self {
+        Self::from_parts(std::slice::from_ref(line))
+    }
+
+    /// Compute the descriptors of a `MultiLineString`
+    ///
+    /// The sinuosity is the total length divided by the sum of the straight
+    /// line distances between the end points of each part. Turns are only
+    /// measured within parts and the curvature bins span the parts in order.
+    pub fn from_multi_line_string(lines: &MultiLineString) -> Self {
+        Self::from_parts(&lines.0)
+    }
+
+    fn from_parts(parts: &[LineString]) -> Self {
+        let len: f64 = parts.iter().map(|l| l.length::<Euclidean>()).sum();
+        let mut curvature = [0.0; CURVATURE_BINS];
+        let ends = parts
+            .iter()
+            .filter_map(|l| Some((*l.0.first()?, *l.0.last()?)))
+            .collect::<Vec<_>>();
+        if ends.is_empty() {
+            return Self {
+                sinuosity: 1.0,
+                turning_angle: 0.0,
+                curvature,
+            };
+        }
+
+        let chord: f64 = ends
+            .iter()
+            .map(|(first, last)| Euclidean::distance(*first, *last))
+            .sum();
+        let sinuosity = if len == 0.0 {
+            1.0
+        } else if chord == 0.0 {
+            f64::INFINITY
+        } else {
+            len / chord
+        };
+
+        let bin_len = len / CURVATURE_BINS as f64;
+        let mut turning_angle = 0.0;
+        let mut dist = 0.0;
+        for line in parts {
+            let mut prev_heading: Option<f64> = None;
+            for li in line.lines() {
+                let seg_len = Euclidean::distance(li.start, li.end);
+                if seg_len == 0.0 {
+                    continue;
+                }
+                let heading = li.dy().atan2(li.dx()).to_degrees();
+                if let Some(prev) = prev_heading {
+                    // smallest signed difference between headings
+                    let turn = ((heading - prev + 540.0) % 360.0 - 180.0).abs();
+                    turning_angle += turn;
+                    let bin = ((dist / bin_len) as usize).min(CURVATURE_BINS - 1);
+                    curvature[bin] += turn;
+                }
+                prev_heading = Some(heading);
+                dist += seg_len;
+            }
+        }
+
+        if bin_len > 0.0 {
+            curvature.iter_mut().for_each(|c| *c /= bin_len);
+        }
+
+        Self {
+            sinuosity,
+            turning_angle,
+            curvature,
+        }
+    }
+
+    /// Similarity of two descriptors in the range `[0, 1]`
+    ///
+    /// This is the product of the ratio of the smaller to the larger
+    /// sinuosity, the ratio of the smaller to the larger turning angle,
+    /// and one minus half of the L1 distance between the normalised curvature
+    /// signatures. The signatures are compared in both directions.
+    pub fn similarity(&self, other: &LineDescriptor) -> f64 {
+        let ratio = |a: f64, b: f64| {
+            if a == b {
+                1.0
+            } else if a.is_infinite() || b.is_infinite() {
+                0.0
+            } else {
+                a.min(b) / a.max(b)
+            }
+        };
+
+        // a small floor avoids penalising nearly straight lines
+        let turn_sim = ratio(self.turning_angle.max(1.0), other.turning_angle.max(1.0));
+        let sin_sim = ratio(self.sinuosity, other.sinuosity);
+
+        let normalise = |c: &[f64; CURVATURE_BINS]| {
+            let total: f64 = c.iter().sum();
+            if total > 0.0 {
+                c.map(|x| x / total)
+            } else {
+                [0.0; CURVATURE_BINS]
+            }
+        };
+        let (a, b) = (normalise(&self.curvature), normalise(&other.curvature));
+        let curv_sim = if a.iter().all(|x| *x == 0.0) || b.iter().all(|x| *x == 0.0) {
+            1.0
+        } else {
+            let forward: f64 = a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum();
+            let backward: f64 = a
+                .iter()
+                .zip(b.iter().rev())
+                .map(|(x, y)| (x - y).abs())
+                .sum();
+            1.0 - forward.min(backward) / 2.0
+        };
+
+        sin_sim * turn_sim * curv_sim
+    }
+}
+
+/// Shape descriptor similarity as a [`MatchConstraint`]
+///
+/// The weight of a pair is the [`LineDescriptor::similarity()`] of the
+/// source and target features. Pairs below `min_similarity` are excluded.
+/// Descriptors describe whole features, so a low `min_similarity` is
+/// recommended when features are expected to match only in part.
+#[derive(Debug, Clone)]
+pub struct DescriptorSimilarity {
+    source: Vec<LineDescriptor>,
+    target: Vec<LineDescriptor>,
+    min_similarity: f64,
+}
+
+impl DescriptorSimilarity {
+    /// Create a constraint from source and target descriptors
+    pub fn new(
+        source: Vec<LineDescriptor>,
+        target: Vec<LineDescriptor>,
+        min_similarity: f64,
+    ) -> Self {
+        Self {
+            source,
+            target,
+            min_similarity,
+        }
+    }
+
+    /// Create a constraint from the descriptors computed by an [`Anime`]
+    ///
+    /// Returns `None` if the descriptors were not computed.
+    pub fn from_anime(anime: &Anime, min_similarity: f64) -> Option<Self> {
+        Some(Self::new(
+            anime.source_descriptors.clone()?,
+            anime.target_descriptors.clone()?,
+            min_similarity,
+        ))
+    }
+}
+
+impl MatchConstraint for DescriptorSimilarity {
+    fn weight(&self, source_index: usize, target_index: usize) -> f64 {
+        let (Some(s), Some(t)) = (self.source.get(source_index), self.target.get(target_index))
+        else {
+            return 1.0;
+        };
+        let sim = s.similarity(t);
+        if sim >= self.min_similarity {
+            sim
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Anime {
+    /// Load source and target geometries and compute descriptors
+    ///
+    /// Like [`Anime::load_geometries()`] but a [`LineDescriptor`] is also
+    /// computed for each source and target feature.
+    pub fn load_geometries_with_descriptors(
+        source: impl Iterator<Item = impl Into<MultiLineString>>,
+        target: impl Iterator<Item = impl Into<MultiLineString>>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+    ) -> Self {
+        let mut source_lens = Vec::new();
+        let mut target_lens = Vec::new();
+        let mut source_descriptors = Vec::new();
+        let mut target_descriptors = Vec::new();
+        let source = source
+            .map(Into::into)
+            .inspect(|s| source_descriptors.push(LineDescriptor::from_multi_line_string(s)));
+        let target = target
+            .map(Into::into)
+            .inspect(|t| target_descriptors.push(LineDescriptor::from_multi_line_string(t)));
+        let source_tree = create_source_rtree(source, &mut source_lens);
+        let target_tree = create_target_rtree(target, &mut target_lens, |_| distance_tolerance);
+
+        let mut anime = Self::from_trees(
+            distance_tolerance,
+            angle_tolerance,
+            FeatureTolerances::default(),
+            MatchOptions::default(),
+            (source_tree, source_lens),
+            (target_tree, target_lens),
+        );
+        anime.source_descriptors = Some(source_descriptors);
+        anime.target_descriptors = Some(target_descriptors);
+        anime
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{cardinality::UnionFind, find_candidate_matches, Anime, MatchesMap};
+use std::{collections::BTreeMap, sync::OnceLock};
+
+/// A pair of overlapping features within a single dataset
+///
+/// Each pair is reported once with `a < b`. The shared length is the
+/// larger of the two directions of the match.
+#[derive(Debug, Clone)]
+pub struct DuplicatePair {
+    pub a: usize,
+    pub b: usize,
+    pub shared_len: f64,
+}
+
+/// The matches of a single set of lines against itself
+///
+/// Source and target indices refer to the same features, which is what
+/// makes duplicate pairs and clusters meaningful. Created with
+/// [`Anime::new_self()`].
+#[derive(Debug, Clone)]
+pub struct SelfMatch {
+    anime: Anime,
+}
+
+impl Anime {
+    /// Match a single set of `LineString`s against itself
+    ///
+    /// The lines are used as both the source and the target. Matches of a
+    /// feature with itself are removed so that the remaining matches only
+    /// describe overlap between distinct features.
+    pub fn new_self(
+        lines: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+    ) -> SelfMatch {
+        let lines = lines.map(Into::into).collect::<Vec<_>>();
+        let mut anime = Self::load_geometries(
+            lines.iter().cloned(),
+            lines.iter().cloned(),
+            distance_tolerance,
+            angle_tolerance,
+        );
+        let mut matches = find_candidate_matches(
+            &anime.source_tree,
+            &anime.target_tree,
+            angle_tolerance,
+            distance_tolerance,
+            &anime.feature_tolerances,
+            None,
+        );
+        remove_self_matches(&mut matches);
+        anime.matches = OnceLock::from(matches);
+        SelfMatch { anime }
+    }
+}
+
+impl SelfMatch {
+    /// The underlying [`Anime`] with the same lines as source and target
+    pub fn anime(&self) -> &Anime {
+        &self.anime
+    }
+
+    pub fn into_anime(self) -> Anime {
+        self.anime
+    }
+
+    fn matches(&self) -> &MatchesMap {
+        // always set by `Anime::new_self()`
+        self.anime.matches.get().expect("self-match has matches")
+    }
+
+    /// Symmetric duplicate pairs
+    ///
+    /// Pairs that only touch, i.e. have no shared length, are not reported.
+    pub fn duplicate_pairs(&self) -> Vec<DuplicatePair> {
+        duplicate_pairs(self.matches())
+    }
+
+    /// Clusters of mutually overlapping features
+    ///
+    /// Each cluster is a sorted vector of feature indices connected by
+    /// [`DuplicatePair`]s. These are candidates for deduplication.
+    pub fn duplicate_clusters(&self) -> Vec<Vec<usize>> {
+        duplicate_clusters(&self.duplicate_pairs(), self.anime.target_lens.len())
+    }
+}
+
+fn remove_self_matches(matches: &mut MatchesMap) {
+    matches.retain(|j, cands| {
+        cands.retain(|ci| ci.source_index != *j);
+        !cands.is_empty()
+    });
+}
+
+fn duplicate_pairs(matches: &MatchesMap) -> Vec<DuplicatePair> {
+    let mut pairs: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+    for (j, cands) in matches.iter() {
+        for ci in cands.iter().filter(|ci| ci.shared_len > 0.0) {
+            let key = (ci.source_index.min(*j), ci.source_index.max(*j));
+            let entry = pairs.entry(key).or_insert(0.0);
+            *entry = entry.max(ci.shared_len);
+        }
+    }
+    pairs
+        .into_iter()
+        .map(|((a, b), shared_len)| DuplicatePair { a, b, shared_len })
+        .collect()
+}
+
+fn duplicate_clusters(pairs: &[DuplicatePair], n: usize) -> Vec<Vec<usize>> {
+    let mut uf = UnionFind::new(n);
+    for p in pairs {
+        uf.union(p.a, p.b);
+    }
+
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for p in pairs {
+        for idx in [p.a, p.b] {
+            let root = uf.find(idx);
+            clusters.entry(root).or_default().push(idx);
+        }
+    }
+
+    let mut res = clusters
+        .into_values()
+        .map(|mut c| {
+            c.sort_unstable();
+            c.dedup();
+            c
+        })
+        .collect::<Vec<_>>();
+    res.sort_unstable_by_key(|c| c[0]);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+
+    #[test]
+    fn duplicates_are_symmetric_and_cluster_transitively() {
+        let lines = [
+            line_string![(x: 0.0, y: 0.0), (x: 10.0, y: 0.0)],
+            line_string![(x: 0.0, y: 0.8), (x: 10.0, y: 0.8)],
+            // overlaps 1 but is too far from 0
+            line_string![(x: 0.0, y: 1.6), (x: 10.0, y: 1.6)],
+            // only touches 0 end to end
+            line_string![(x: 10.0, y: 0.0), (x: 20.0, y: 0.0)],
+            line_string![(x: 0.0, y: 50.0), (x: 10.0, y: 50.0)],
+        ];
+        let dupes = Anime::new_self(lines.into_iter(), 1.0, 5.0);
+
+        let matches = dupes.anime().matches.get().unwrap();
+        assert!(matches
+            .iter()
+            .all(|(j, cands)| cands.iter().all(|ci| ci.source_index != *j)));
+
+        let pairs = dupes
+            .duplicate_pairs()
+            .iter()
+            .map(|p| (p.a, p.b, p.shared_len))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, vec![(0, 1, 10.0), (1, 2, 10.0)]);
+        assert_eq!(dupes.duplicate_clusters(), vec![vec![0, 1, 2]]);
+    }
+}
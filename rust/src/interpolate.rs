@@ -1,4 +1,4 @@
-use crate::{Anime, AnimeError};
+use crate::{Anime, AnimeError, MatchCandidate, MatchesMap};
 
 /// Intensive or Extensive Interpolation
 ///
@@ -7,6 +7,7 @@ use crate::{Anime, AnimeError};
 ///
 /// Extensive interpolation weights the attribute by the shared
 /// length divided by the length of the target geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Tensive {
     In,
     Ex,
@@ -24,10 +25,36 @@ impl Anime {
         var: &[f64],
         tensive: Tensive,
     ) -> Result<Vec<InterpolatedValue>, AnimeError> {
-        match tensive {
-            Tensive::In => self.interpolate_intensive(var),
-            Tensive::Ex => self.interpolate_extensive(var),
-        }
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        interpolate(
+            matches,
+            &self.source_lens,
+            &self.target_lens,
+            var,
+            tensive,
+            false,
+        )
+    }
+
+    /// Perform numeric attribute interpolation weighting each match by its confidence
+    ///
+    /// The shared length of each match is multiplied by its
+    /// [`MatchCandidate::confidence`], so matches down-weighted by a
+    /// [`crate::constraint::MatchConstraint`] contribute less.
+    pub fn interpolate_with_confidence(
+        &self,
+        var: &[f64],
+        tensive: Tensive,
+    ) -> Result<Vec<InterpolatedValue>, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        interpolate(
+            matches,
+            &self.source_lens,
+            &self.target_lens,
+            var,
+            tensive,
+            true,
+        )
     }
 
     /// Extensive Interpolation from the source to the target
@@ -44,36 +71,8 @@ impl Anime {
         &self,
         source_var: &[f64],
     ) -> Result<Vec<InterpolatedValue>, AnimeError> {
-        // Check if `source_var` matches the number of source geometries
-        if source_var.len() != self.source_lens.len() {
-            return Err(AnimeError::IncorrectLength);
-        }
-
-        // Retrieve matches (or return error if not found)
         let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
-
-        // Interpolate extensive variable
-        let res = matches
-            .iter()
-            .map(|(target_id, matches)| {
-                let value = matches.iter().fold(0.0, |acc, mi| {
-                    let source_idx = mi.source_index;
-                    let shared_len = mi.shared_len;
-
-                    // Weight = shared length / total length of source geometry
-                    let wt = shared_len / self.source_lens[source_idx];
-
-                    // Weighted contribution of source variable
-                    acc + (source_var[source_idx] * wt)
-                });
-                InterpolatedValue {
-                    target_id: *target_id,
-                    value,
-                }
-            })
-            .collect::<Vec<_>>();
-
-        Ok(res)
+        interpolate_extensive(matches, &self.source_lens, source_var, false)
     }
 
     /// Intensive Interpolation from the source to the target
@@ -101,47 +100,120 @@ impl Anime {
         &self,
         source_var: &[f64],
     ) -> Result<Vec<InterpolatedValue>, AnimeError> {
-        let nv = source_var.len();
-        let n_tar = self.source_lens.len(); // Assuming target_lens represent target lengths.
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        interpolate_intensive(
+            matches,
+            &self.source_lens,
+            &self.target_lens,
+            source_var,
+            false,
+        )
+    }
+}
 
-        if nv != n_tar {
-            return Err(AnimeError::IncorrectLength);
+/// Extensive or intensive interpolation. See [`Anime::interpolate()`].
+pub(crate) fn interpolate(
+    matches: &MatchesMap,
+    source_lens: &[f64],
+    target_lens: &[f64],
+    var: &[f64],
+    tensive: Tensive,
+    use_confidence: bool,
+) -> Result<Vec<InterpolatedValue>, AnimeError> {
+    match tensive {
+        Tensive::In => {
+            interpolate_intensive(matches, source_lens, target_lens, var, use_confidence)
         }
+        Tensive::Ex => interpolate_extensive(matches, source_lens, var, use_confidence),
+    }
+}
 
-        // Ensure matches are loaded
-        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+/// The shared length of a match, optionally scaled by its confidence
+fn shared_len(mi: &MatchCandidate, use_confidence: bool) -> f64 {
+    if use_confidence {
+        mi.shared_len * mi.confidence
+    } else {
+        mi.shared_len
+    }
+}
 
-        let res = matches
-            .iter()
-            .map(|(target_idx, matches)| {
-                // Calculate the weighted sum of the source variable values and normalize by the total weight
-                let (numerator, denominator) =
-                    matches.iter().fold((0.0, 0.0), |(acc_num, acc_den), mi| {
-                        let source_idx = mi.source_index;
-
-                        // Weight based on shared length and target length
-                        let wt =
-                            mi.shared_len / self.target_lens.get(*target_idx as usize).unwrap(); // Using target length for weight
-                        let weighted_value = source_var[source_idx] * wt;
-
-                        // Update the numerator (weighted sum) and denominator (total weight)
-                        (acc_num + weighted_value, acc_den + wt)
-                    });
-
-                // If the total weight is greater than zero, compute the weighted mean
-                let value = if denominator > 0.0 {
-                    numerator / denominator
-                } else {
-                    0.0 // If no overlap, return 0 or handle differently
-                };
-
-                InterpolatedValue {
-                    target_id: *target_idx,
-                    value,
-                }
-            })
-            .collect::<Vec<_>>();
-
-        Ok(res)
+/// Extensive interpolation. See [`Anime::interpolate_extensive()`].
+pub(crate) fn interpolate_extensive(
+    matches: &MatchesMap,
+    source_lens: &[f64],
+    source_var: &[f64],
+    use_confidence: bool,
+) -> Result<Vec<InterpolatedValue>, AnimeError> {
+    // Check if `source_var` matches the number of source geometries
+    if source_var.len() != source_lens.len() {
+        return Err(AnimeError::IncorrectLength);
     }
+
+    // Interpolate extensive variable
+    let res = matches
+        .iter()
+        .map(|(target_id, matches)| {
+            let value = matches.iter().fold(0.0, |acc, mi| {
+                let source_idx = mi.source_index;
+                let shared_len = shared_len(mi, use_confidence);
+
+                // Weight = shared length / total length of source geometry
+                let wt = shared_len / source_lens[source_idx];
+
+                // Weighted contribution of source variable
+                acc + (source_var[source_idx] * wt)
+            });
+            InterpolatedValue {
+                target_id: *target_id,
+                value,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(res)
+}
+
+/// Intensive interpolation. See [`Anime::interpolate_intensive()`].
+pub(crate) fn interpolate_intensive(
+    matches: &MatchesMap,
+    source_lens: &[f64],
+    target_lens: &[f64],
+    source_var: &[f64],
+    use_confidence: bool,
+) -> Result<Vec<InterpolatedValue>, AnimeError> {
+    if source_var.len() != source_lens.len() {
+        return Err(AnimeError::IncorrectLength);
+    }
+
+    let res = matches
+        .iter()
+        .map(|(target_idx, matches)| {
+            // Calculate the weighted sum of the source variable values and normalize by the total weight
+            let (numerator, denominator) =
+                matches.iter().fold((0.0, 0.0), |(acc_num, acc_den), mi| {
+                    let source_idx = mi.source_index;
+
+                    // Weight based on shared length and target length
+                    let wt = shared_len(mi, use_confidence) / target_lens.get(*target_idx).unwrap(); // Using target length for weight
+                    let weighted_value = source_var[source_idx] * wt;
+
+                    // Update the numerator (weighted sum) and denominator (total weight)
+                    (acc_num + weighted_value, acc_den + wt)
+                });
+
+            // If the total weight is greater than zero, compute the weighted mean
+            let value = if denominator > 0.0 {
+                numerator / denominator
+            } else {
+                0.0 // If no overlap, return 0 or handle differently
+            };
+
+            InterpolatedValue {
+                target_id: *target_idx,
+                value,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(res)
 }
@@ -0,0 +1,444 @@
+//! GeoArrow arrays, Arrow `RecordBatch`es and GeoParquet files
+use super::{attr_type, io_err, to_lines, AttrType, AttrValue, LineFeatures};
+use crate::{
+    interpolate::{InterpolatedValue, Tensive},
+    model::MatchModel,
+    table::{match_table, MatchRow},
+    Anime, AnimeError, Side,
+};
+use arrow_array::{
+    cast::AsArray, types::*, Array, ArrayRef, ArrowPrimitiveType, BooleanArray, Float64Array,
+    Int64Array, RecordBatch, StringArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema};
+use geo_traits::to_geo::ToGeoGeometry;
+use geo_types::MultiLineString;
+use geoarrow_array::{
+    array::from_arrow_array, builder::MultiLineStringBuilder, downcast_geoarrow_array,
+    GeoArrowArray, GeoArrowArrayAccessor,
+};
+use geoarrow_schema::{Dimension, GeoArrowType, MultiLineStringType};
+use geoparquet::{
+    reader::{GeoParquetReaderBuilder, GeoParquetRecordBatchReader},
+    writer::{GeoParquetRecordBatchEncoder, GeoParquetWriterOptions},
+};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    file::reader::ChunkReader,
+};
+use std::{fs::File, io::Write, path::Path, sync::Arc};
+
+fn collect_lines<'a>(
+    array: &'a impl GeoArrowArrayAccessor<'a>,
+) -> Result<Vec<MultiLineString>, AnimeError> {
+    array
+        .iter()
+        .enumerate()
+        .map(|(i, geom)| {
+            let geom = geom
+                .ok_or(AnimeError::UnsupportedGeometry {
+                    side: None,
+                    index: i,
+                })?
+                .map_err(io_err)?;
+            to_lines(i, geom.to_geometry())
+        })
+        .collect()
+}
+
+/// Read the geometries of a GeoArrow array
+///
+/// Native `LineString` and `MultiLineString` arrays are supported, as are
+/// WKB, WKT and mixed geometry arrays whose geometries are all lines. Null
+/// geometries are an error.
+pub fn geoarrow_lines(array: &dyn GeoArrowArray) -> Result<Vec<MultiLineString>, AnimeError> {
+    downcast_geoarrow_array!(array, collect_lines)
+}
+
+impl Anime {
+    /// Load source and target GeoArrow arrays and find matches
+    pub fn from_geoarrow(
+        source: &dyn GeoArrowArray,
+        target: &dyn GeoArrowArray,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+    ) -> Result<Self, AnimeError> {
+        let source = geoarrow_lines(source).map_err(|e| e.with_side(Side::Source))?;
+        let target = geoarrow_lines(target).map_err(|e| e.with_side(Side::Target))?;
+        Ok(Anime::new(
+            source.into_iter(),
+            target.into_iter(),
+            distance_tolerance,
+            angle_tolerance,
+        ))
+    }
+
+    /// The matches as a `RecordBatch`. See [`match_table_batch()`].
+    pub fn match_table_batch(&self) -> Result<RecordBatch, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        match_table_batch(&match_table(matches, &self.source_lens, &self.target_lens))
+    }
+
+    /// Interpolate a numeric variable into a `RecordBatch`
+    ///
+    /// The batch has a `target_id` and a `value` column with one row per
+    /// matched target.
+    pub fn interpolate_batch(
+        &self,
+        var: &[f64],
+        tensive: Tensive,
+    ) -> Result<RecordBatch, AnimeError> {
+        interpolated_batch(&self.interpolate(var, tensive)?)
+    }
+}
+
+/// A `target_id` and `value` batch of interpolated values
+fn interpolated_batch(values: &[InterpolatedValue]) -> Result<RecordBatch, AnimeError> {
+    let schema = Schema::new(vec![
+        Field::new("target_id", DataType::UInt64, false),
+        Field::new("value", DataType::Float64, false),
+    ]);
+    let target_ids = values.iter().map(|v| v.target_id as u64);
+    let values = values.iter().map(|v| v.value);
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(UInt64Array::from_iter_values(target_ids)),
+            Arc::new(Float64Array::from_iter_values(values)),
+        ],
+    )
+    .map_err(io_err)
+}
+
+impl MatchModel {
+    /// The matches as a `RecordBatch`. See [`match_table_batch()`].
+    pub fn match_table_batch(&self) -> Result<RecordBatch, AnimeError> {
+        match_table_batch(&self.match_table())
+    }
+
+    /// Interpolate a numeric variable into a `RecordBatch`. See [`Anime::interpolate_batch()`].
+    pub fn interpolate_batch(
+        &self,
+        var: &[f64],
+        tensive: Tensive,
+    ) -> Result<RecordBatch, AnimeError> {
+        interpolated_batch(&self.interpolate(var, tensive)?)
+    }
+}
+
+/// Convert a match table into a `RecordBatch`
+///
+/// The batch has the same columns as [`MatchRow`]. The ids are `UInt64` and
+/// all other columns are `Float64`.
+pub fn match_table_batch(rows: &[MatchRow]) -> Result<RecordBatch, AnimeError> {
+    let schema = Schema::new(vec![
+        Field::new("target_id", DataType::UInt64, false),
+        Field::new("source_id", DataType::UInt64, false),
+        Field::new("shared_len", DataType::Float64, false),
+        Field::new("source_weighted", DataType::Float64, false),
+        Field::new("target_weighted", DataType::Float64, false),
+        Field::new("confidence", DataType::Float64, false),
+    ]);
+    let ids = |f: fn(&MatchRow) -> usize| {
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|r| f(r) as u64),
+        )) as ArrayRef
+    };
+    let floats = |f: fn(&MatchRow) -> f64| {
+        Arc::new(Float64Array::from_iter_values(rows.iter().map(f))) as ArrayRef
+    };
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            ids(|r| r.target_id),
+            ids(|r| r.source_id),
+            floats(|r| r.shared_len),
+            floats(|r| r.source_weighted),
+            floats(|r| r.target_weighted),
+            floats(|r| r.confidence),
+        ],
+    )
+    .map_err(io_err)
+}
+
+fn primitive<T: ArrowPrimitiveType>(
+    array: &dyn Array,
+    f: impl Fn(T::Native) -> AttrValue,
+) -> Vec<AttrValue> {
+    array
+        .as_primitive::<T>()
+        .iter()
+        .map(|v| v.map_or(AttrValue::Null, &f))
+        .collect()
+}
+
+fn to_attrs(array: &dyn Array) -> Vec<AttrValue> {
+    let text = |v: Option<&str>| v.map_or(AttrValue::Null, |s| AttrValue::String(s.to_string()));
+    match array.data_type() {
+        DataType::Boolean => array
+            .as_boolean()
+            .iter()
+            .map(|v| v.map_or(AttrValue::Null, AttrValue::Bool))
+            .collect(),
+        DataType::Int8 => primitive::<Int8Type>(array, |v| AttrValue::Int(v as i64)),
+        DataType::Int16 => primitive::<Int16Type>(array, |v| AttrValue::Int(v as i64)),
+        DataType::Int32 => primitive::<Int32Type>(array, |v| AttrValue::Int(v as i64)),
+        DataType::Int64 => primitive::<Int64Type>(array, AttrValue::Int),
+        DataType::UInt8 => primitive::<UInt8Type>(array, |v| AttrValue::Int(v as i64)),
+        DataType::UInt16 => primitive::<UInt16Type>(array, |v| AttrValue::Int(v as i64)),
+        DataType::UInt32 => primitive::<UInt32Type>(array, |v| AttrValue::Int(v as i64)),
+        DataType::UInt64 => primitive::<UInt64Type>(array, |v| {
+            i64::try_from(v).map_or(AttrValue::Float(v as f64), AttrValue::Int)
+        }),
+        DataType::Float32 => primitive::<Float32Type>(array, |v| AttrValue::Float(v as f64)),
+        DataType::Float64 => primitive::<Float64Type>(array, AttrValue::Float),
+        DataType::Utf8 => array.as_string::<i32>().iter().map(text).collect(),
+        DataType::LargeUtf8 => array.as_string::<i64>().iter().map(text).collect(),
+        DataType::Utf8View => array.as_string_view().iter().map(text).collect(),
+        // other types can't be interpolated
+        _ => vec![AttrValue::Null; array.len()],
+    }
+}
+
+/// Convert a `RecordBatch` into [`LineFeatures`]
+///
+/// The first column with GeoArrow extension metadata holds the geometries.
+/// Every other column becomes an attribute column. Columns that are not
+/// boolean, numeric or strings are read as nulls.
+pub fn features_from_batch(batch: &RecordBatch) -> Result<LineFeatures, AnimeError> {
+    let schema = batch.schema();
+    let geom_idx = schema
+        .fields()
+        .iter()
+        .position(|f| matches!(GeoArrowType::from_extension_field(f), Ok(Some(_))))
+        .ok_or_else(|| AnimeError::ColumnNotFound("geometry".to_string()))?;
+    let geoms = from_arrow_array(batch.column(geom_idx), schema.field(geom_idx)).map_err(io_err)?;
+
+    let attributes = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .enumerate()
+        .filter(|(i, _)| *i != geom_idx)
+        .map(|(_, (field, col))| (field.name().clone(), to_attrs(col)))
+        .collect();
+
+    Ok(LineFeatures {
+        geometries: geoarrow_lines(geoms.as_ref())?,
+        attributes,
+    })
+}
+
+/// Convert [`LineFeatures`] into a `RecordBatch`
+///
+/// The geometries are a GeoArrow `MultiLineString` column named `geometry`
+/// followed by one column per attribute. Each attribute column has the
+/// narrowest type that holds all of its values.
+pub fn features_to_batch(features: &LineFeatures) -> Result<RecordBatch, AnimeError> {
+    let typ = MultiLineStringType::new(Dimension::XY, Default::default());
+    let geoms =
+        MultiLineStringBuilder::from_multi_line_strings(&features.geometries, typ.clone()).finish();
+
+    let mut fields = vec![typ.to_field("geometry", true)];
+    let mut columns = vec![geoms.into_array_ref()];
+    for (name, values) in &features.attributes {
+        let (data_type, array): (DataType, ArrayRef) = match attr_type(values) {
+            AttrType::Bool => (
+                DataType::Boolean,
+                Arc::new(
+                    values
+                        .iter()
+                        .map(|v| match v {
+                            AttrValue::Bool(b) => Some(*b),
+                            _ => None,
+                        })
+                        .collect::<BooleanArray>(),
+                ),
+            ),
+            AttrType::Int => (
+                DataType::Int64,
+                Arc::new(
+                    values
+                        .iter()
+                        .map(|v| match v {
+                            AttrValue::Int(i) => Some(*i),
+                            _ => None,
+                        })
+                        .collect::<Int64Array>(),
+                ),
+            ),
+            AttrType::Float => (
+                DataType::Float64,
+                Arc::new(
+                    values
+                        .iter()
+                        .map(AttrValue::as_f64)
+                        .collect::<Float64Array>(),
+                ),
+            ),
+            AttrType::String => (
+                DataType::Utf8,
+                Arc::new(
+                    values
+                        .iter()
+                        .map(|v| match v {
+                            AttrValue::Null => None,
+                            v => Some(v.to_string()),
+                        })
+                        .collect::<StringArray>(),
+                ),
+            ),
+        };
+        fields.push(Field::new(name, data_type, true));
+        columns.push(array);
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(io_err)
+}
+
+/// Read [`LineFeatures`] from a GeoParquet file
+pub fn read_geoparquet<R: ChunkReader + 'static>(reader: R) -> Result<LineFeatures, AnimeError> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(reader).map_err(io_err)?;
+    let metadata = builder
+        .geoparquet_metadata()
+        .ok_or_else(|| AnimeError::Io("file has no GeoParquet metadata".to_string()))?
+        .map_err(io_err)?;
+    let schema = builder
+        .geoarrow_schema(&metadata, true, Default::default())
+        .map_err(io_err)?;
+    let batches = GeoParquetRecordBatchReader::try_new(builder.build().map_err(io_err)?, schema)
+        .map_err(io_err)?;
+
+    let mut features = LineFeatures::default();
+    for batch in batches {
+        let offset = features.len();
+        // geometry errors are indexed within their batch
+        let batch = features_from_batch(&batch.map_err(io_err)?).map_err(|e| match e {
+            AnimeError::UnsupportedGeometry { side, index } => AnimeError::UnsupportedGeometry {
+                side,
+                index: index + offset,
+            },
+            e => e,
+        })?;
+        features.geometries.extend(batch.geometries);
+        for (name, values) in batch.attributes {
+            features.attributes.entry(name).or_default().extend(values);
+        }
+    }
+    Ok(features)
+}
+
+/// Read [`LineFeatures`] from a GeoParquet file. See [`read_geoparquet()`].
+pub fn read_geoparquet_file(path: impl AsRef<Path>) -> Result<LineFeatures, AnimeError> {
+    let file = File::open(path).map_err(io_err)?;
+    read_geoparquet(file)
+}
+
+/// Write [`LineFeatures`], such as enriched targets, as a GeoParquet file
+///
+/// See [`features_to_batch()`] for the schema.
+pub fn write_geoparquet(
+    features: &LineFeatures,
+    writer: impl Write + Send,
+) -> Result<(), AnimeError> {
+    let batch = features_to_batch(features)?;
+    let mut encoder =
+        GeoParquetRecordBatchEncoder::try_new(&batch.schema(), &GeoParquetWriterOptions::default())
+            .map_err(io_err)?;
+    let mut writer = ArrowWriter::try_new(writer, encoder.target_schema(), None).map_err(io_err)?;
+    let encoded = encoder.encode_record_batch(&batch).map_err(io_err)?;
+    writer.write(&encoded).map_err(io_err)?;
+    writer.append_key_value_metadata(encoder.into_keyvalue().map_err(io_err)?);
+    writer.finish().map_err(io_err)?;
+    Ok(())
+}
+
+/// Write [`LineFeatures`] to a GeoParquet file. See [`write_geoparquet()`].
+pub fn write_geoparquet_file(
+    features: &LineFeatures,
+    path: impl AsRef<Path>,
+) -> Result<(), AnimeError> {
+    let file = File::create(path).map_err(io_err)?;
+    write_geoparquet(features, file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+    use std::collections::BTreeMap;
+
+    fn features() -> LineFeatures {
+        let geometries = vec![
+            MultiLineString::new(vec![line_string![(x: 0.0, y: 0.0), (x: 10.0, y: 0.0)]]),
+            MultiLineString::new(vec![
+                line_string![(x: 0.0, y: 5.0), (x: 10.0, y: 5.0)],
+                line_string![(x: 10.0, y: 5.0), (x: 10.0, y: 15.0)],
+            ]),
+        ];
+        let attributes = BTreeMap::from([
+            (
+                "name".to_string(),
+                vec![AttrValue::String("Main St".to_string()), AttrValue::Null],
+            ),
+            (
+                "lanes".to_string(),
+                vec![AttrValue::Int(2), AttrValue::Null],
+            ),
+            (
+                "speed".to_string(),
+                vec![AttrValue::Float(30.5), AttrValue::Float(50.0)],
+            ),
+            (
+                "oneway".to_string(),
+                vec![AttrValue::Bool(true), AttrValue::Bool(false)],
+            ),
+        ]);
+        LineFeatures {
+            geometries,
+            attributes,
+        }
+    }
+
+    #[test]
+    fn features_round_trip_through_a_batch() {
+        let features = features();
+        let batch = features_to_batch(&features).unwrap();
+        assert_eq!(batch.schema().field(0).name(), "geometry");
+        assert_eq!(
+            batch.schema().field_with_name("lanes").unwrap().data_type(),
+            &DataType::Int64
+        );
+
+        let read = features_from_batch(&batch).unwrap();
+        assert_eq!(read.geometries, features.geometries);
+        assert_eq!(read.attributes, features.attributes);
+    }
+
+    #[test]
+    fn features_round_trip_through_geoparquet() {
+        let features = features();
+        let path = std::env::temp_dir().join(format!("anime-{}.parquet", std::process::id()));
+        write_geoparquet_file(&features, &path).unwrap();
+        let read = read_geoparquet_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let read = read.unwrap();
+        assert_eq!(read.geometries, features.geometries);
+        assert_eq!(read.attributes, features.attributes);
+    }
+
+    #[test]
+    fn geoarrow_arrays_are_matched() {
+        let features = features();
+        let typ = MultiLineStringType::new(Dimension::XY, Default::default());
+        let array =
+            MultiLineStringBuilder::from_multi_line_strings(&features.geometries, typ).finish();
+        let anime = Anime::from_geoarrow(&array, &array, 1.0, 5.0).unwrap();
+
+        let batch = anime.match_table_batch().unwrap();
+        assert_eq!(batch.num_columns(), 6);
+        let target_ids = batch.column(0).as_primitive::<UInt64Type>();
+        assert!((0..2).all(|j| target_ids.values().contains(&j)));
+    }
+}
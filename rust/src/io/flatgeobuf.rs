@@ -0,0 +1,268 @@
+//! FlatGeobuf files of `LineString` or `MultiLineString` features
+use super::{attr_type, io_err, to_lines, AttrType, AttrValue, LineFeatures};
+use crate::{Anime, AnimeError, Side};
+use flatgeobuf::{
+    ColumnType, FallibleStreamingIterator, FeatureIter, FgbReader, FgbWriter, GeometryType,
+    Seekable,
+};
+use geo_types::{Coord, Geometry, MultiLineString, Rect};
+use geozero::{ColumnValue, FeatureProperties, PropertyProcessor, ToGeo};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, Write},
+    path::Path,
+};
+
+fn to_attr(value: &ColumnValue) -> AttrValue {
+    match value {
+        ColumnValue::Byte(v) => AttrValue::Int(*v as i64),
+        ColumnValue::UByte(v) => AttrValue::Int(*v as i64),
+        ColumnValue::Bool(v) => AttrValue::Bool(*v),
+        ColumnValue::Short(v) => AttrValue::Int(*v as i64),
+        ColumnValue::UShort(v) => AttrValue::Int(*v as i64),
+        ColumnValue::Int(v) => AttrValue::Int(*v as i64),
+        ColumnValue::UInt(v) => AttrValue::Int(*v as i64),
+        ColumnValue::Long(v) => AttrValue::Int(*v),
+        ColumnValue::ULong(v) => {
+            i64::try_from(*v).map_or(AttrValue::Float(*v as f64), AttrValue::Int)
+        }
+        ColumnValue::Float(v) => AttrValue::Float(*v as f64),
+        ColumnValue::Double(v) => AttrValue::Float(*v),
+        ColumnValue::String(v) | ColumnValue::Json(v) | ColumnValue::DateTime(v) => {
+            AttrValue::String(v.to_string())
+        }
+        // binary values can't be interpolated
+        ColumnValue::Binary(_) => AttrValue::Null,
+    }
+}
+
+/// The properties of a single feature in column order
+struct Row<'a> {
+    names: &'a [String],
+    values: Vec<AttrValue>,
+}
+
+impl PropertyProcessor for Row<'_> {
+    fn property(
+        &mut self,
+        _i: usize,
+        name: &str,
+        value: &ColumnValue,
+    ) -> geozero::error::Result<bool> {
+        if let Some(pos) = self.names.iter().position(|n| n == name) {
+            self.values[pos] = to_attr(value);
+        }
+        Ok(false)
+    }
+}
+
+/// Select all features or, using the packed Hilbert R-tree, those intersecting `bbox`
+fn select<R: Read + Seek>(
+    reader: R,
+    bbox: Option<Rect>,
+) -> Result<FeatureIter<R, Seekable>, AnimeError> {
+    let fgb = FgbReader::open(reader).map_err(io_err)?;
+    match bbox {
+        Some(b) => fgb.select_bbox(b.min().x, b.min().y, b.max().x, b.max().y),
+        None => fgb.select_all(),
+    }
+    .map_err(io_err)
+}
+
+/// Stream the geometries of the selected features
+///
+/// The iterator stops at the first feature that can't be read and stores
+/// the error in `error`.
+fn stream_lines<'a, R: Read + Seek>(
+    features: &'a mut FeatureIter<R, Seekable>,
+    error: &'a mut Option<AnimeError>,
+) -> impl Iterator<Item = MultiLineString> + 'a {
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        let lines = match features.next() {
+            Ok(Some(f)) => f
+                .to_geo()
+                .map_err(|_| AnimeError::UnsupportedGeometry {
+                    side: None,
+                    index: i,
+                })
+                .and_then(|g| to_lines(i, g)),
+            Ok(None) => return None,
+            Err(e) => Err(io_err(e)),
+        };
+        i += 1;
+        lines.map_err(|e| *error = Some(e)).ok()
+    })
+}
+
+/// Read [`LineFeatures`] from a FlatGeobuf file
+///
+/// When `bbox` is provided only the features intersecting it are read. This
+/// requires the file to have a spatial index.
+pub fn read_flatgeobuf(
+    reader: impl Read + Seek,
+    bbox: Option<Rect>,
+) -> Result<LineFeatures, AnimeError> {
+    let mut selected = select(reader, bbox)?;
+    let names = selected
+        .header()
+        .columns()
+        .map(|cols| {
+            cols.iter()
+                .map(|c| c.name().to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut features = LineFeatures::default();
+    let mut columns = vec![Vec::new(); names.len()];
+    let mut i = 0;
+    while let Some(f) = selected.next().map_err(io_err)? {
+        let geom = f.to_geo().map_err(|_| AnimeError::UnsupportedGeometry {
+            side: None,
+            index: i,
+        })?;
+        features.geometries.push(to_lines(i, geom)?);
+
+        let mut row = Row {
+            names: &names,
+            values: vec![AttrValue::Null; names.len()],
+        };
+        f.process_properties(&mut row).map_err(io_err)?;
+        columns
+            .iter_mut()
+            .zip(row.values)
+            .for_each(|(c, v)| c.push(v));
+        i += 1;
+    }
+
+    features.attributes = names.into_iter().zip(columns).collect();
+    Ok(features)
+}
+
+/// Read [`LineFeatures`] from a FlatGeobuf file. See [`read_flatgeobuf()`].
+pub fn read_flatgeobuf_file(
+    path: impl AsRef<Path>,
+    bbox: Option<Rect>,
+) -> Result<LineFeatures, AnimeError> {
+    let file = File::open(path).map_err(io_err)?;
+    read_flatgeobuf(BufReader::new(file), bbox)
+}
+
+fn column_type(attr_type: AttrType) -> ColumnType {
+    match attr_type {
+        AttrType::Bool => ColumnType::Bool,
+        AttrType::Int => ColumnType::Long,
+        AttrType::Float => ColumnType::Double,
+        AttrType::String => ColumnType::String,
+    }
+}
+
+/// Write [`LineFeatures`], such as enriched targets, as a FlatGeobuf file
+///
+/// Geometries are written as `MultiLineString`s with a spatial index. Null
+/// values are omitted and each column has the narrowest type that holds all
+/// of its values.
+pub fn write_flatgeobuf(
+    features: &LineFeatures,
+    name: &str,
+    writer: impl Write,
+) -> Result<(), AnimeError> {
+    let mut fgb = FgbWriter::create(name, GeometryType::MultiLineString).map_err(io_err)?;
+    let columns = features
+        .attributes
+        .iter()
+        .map(|(name, values)| (name, values, column_type(attr_type(values))))
+        .collect::<Vec<_>>();
+    for (name, _, col_type) in &columns {
+        fgb.add_column(name, *col_type, |_, col| col.nullable = true);
+    }
+
+    for (i, lines) in features.geometries.iter().enumerate() {
+        let mut res = Ok(());
+        fgb.add_feature_geom(Geometry::MultiLineString(lines.clone()), |feat| {
+            for (j, (name, values, col_type)) in columns.iter().enumerate() {
+                let text;
+                let value = match (*col_type, &values[i]) {
+                    (_, AttrValue::Null) => continue,
+                    (ColumnType::Bool, AttrValue::Bool(v)) => ColumnValue::Bool(*v),
+                    (ColumnType::Long, AttrValue::Int(v)) => ColumnValue::Long(*v),
+                    (ColumnType::Double, v) => ColumnValue::Double(v.as_f64().unwrap_or(f64::NAN)),
+                    (_, v) => {
+                        text = v.to_string();
+                        ColumnValue::String(&text)
+                    }
+                };
+                if let Err(e) = feat.property(j, name, &value) {
+                    res = Err(e);
+                }
+            }
+        })
+        .map_err(io_err)?;
+        res.map_err(io_err)?;
+    }
+
+    let mut writer = BufWriter::new(writer);
+    fgb.write(&mut writer).map_err(io_err)?;
+    writer.flush().map_err(io_err)
+}
+
+/// Write [`LineFeatures`] to a FlatGeobuf file. See [`write_flatgeobuf()`].
+pub fn write_flatgeobuf_file(
+    features: &LineFeatures,
+    path: impl AsRef<Path>,
+) -> Result<(), AnimeError> {
+    let path = path.as_ref();
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let file = File::create(path).map_err(io_err)?;
+    write_flatgeobuf(features, name, file)
+}
+
+impl Anime {
+    /// Stream source and target FlatGeobuf features into the trees and find matches
+    ///
+    /// Only the geometries are read, one feature at a time. When `bbox` is
+    /// provided only source and target features intersecting it are loaded
+    /// using each file's packed Hilbert R-tree. Feature indices are positions
+    /// among the loaded features, matching [`read_flatgeobuf()`] with the same
+    /// `bbox`.
+    ///
+    /// Sources outside of the `bbox` are not loaded even if they are within
+    /// the `distance_tolerance` of a target inside it. Use [`pad_bbox()`] to
+    /// load them.
+    pub fn from_flatgeobuf(
+        source: impl Read + Seek,
+        target: impl Read + Seek,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+        bbox: Option<Rect>,
+    ) -> Result<Self, AnimeError> {
+        let mut source = select(source, bbox)?;
+        let mut target = select(target, bbox)?;
+        let mut source_error = None;
+        let mut target_error = None;
+        let mut anime = Anime::load_geometries(
+            stream_lines(&mut source, &mut source_error),
+            stream_lines(&mut target, &mut target_error),
+            distance_tolerance,
+            angle_tolerance,
+        );
+
+        let source_error = source_error.map(|e| e.with_side(Side::Source));
+        let target_error = target_error.map(|e| e.with_side(Side::Target));
+        if let Some(e) = source_error.or(target_error) {
+            return Err(e);
+        }
+        anime.find_matches()?;
+        Ok(anime)
+    }
+}
+
+/// Grow a bounding box by `distance` in each direction
+pub fn pad_bbox(bbox: Rect, distance: f64) -> Rect {
+    let pad = Coord {
+        x: distance,
+        y: distance,
+    };
+    Rect::new(bbox.min() - pad, bbox.max() + pad)
+}
@@ -0,0 +1,200 @@
+//! GeoJSON FeatureCollections of `LineString` or `MultiLineString` features
+use super::{io_err, AttrValue, LineFeatures};
+use crate::{table::MatchRow, AnimeError};
+use geo_types::{LineString, MultiLineString};
+use geojson::{Feature, FeatureCollection, GeoJson, JsonObject, JsonValue, Value};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+fn to_attr(value: &JsonValue) -> AttrValue {
+    match value {
+        JsonValue::Null => AttrValue::Null,
+        JsonValue::Bool(b) => AttrValue::Bool(*b),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => AttrValue::Int(i),
+            None => n.as_f64().map_or(AttrValue::Null, AttrValue::Float),
+        },
+        JsonValue::String(s) => AttrValue::String(s.clone()),
+        // nested values are kept as their JSON text
+        v => AttrValue::String(v.to_string()),
+    }
+}
+
+fn to_json(value: &AttrValue) -> JsonValue {
+    match value {
+        AttrValue::Null => JsonValue::Null,
+        AttrValue::Bool(b) => JsonValue::Bool(*b),
+        AttrValue::Int(i) => JsonValue::from(*i),
+        // non-finite floats are not valid JSON and become null
+        AttrValue::Float(f) => {
+            serde_json::Number::from_f64(*f).map_or(JsonValue::Null, JsonValue::Number)
+        }
+        AttrValue::String(s) => JsonValue::String(s.clone()),
+    }
+}
+
+fn to_geometry(lines: &MultiLineString) -> geojson::Geometry {
+    match lines.0.as_slice() {
+        [line] => geojson::Geometry::from(line),
+        _ => geojson::Geometry::from(lines),
+    }
+}
+
+/// Convert a FeatureCollection into [`LineFeatures`]
+///
+/// Every feature must have a `LineString` or `MultiLineString` geometry.
+pub fn from_feature_collection(fc: &FeatureCollection) -> Result<LineFeatures, AnimeError> {
+    let n = fc.features.len();
+    let mut features = LineFeatures::default();
+
+    for (i, f) in fc.features.iter().enumerate() {
+        let lines = match f.geometry.as_ref().map(|g| &g.value) {
+            Some(v @ Value::LineString(_)) => {
+                LineString::try_from(v).map(|l| MultiLineString::new(vec![l]))
+            }
+            Some(v @ Value::MultiLineString(_)) => MultiLineString::try_from(v),
+            _ => {
+                return Err(AnimeError::UnsupportedGeometry {
+                    side: None,
+                    index: i,
+                })
+            }
+        }
+        .map_err(|_| AnimeError::UnsupportedGeometry {
+            side: None,
+            index: i,
+        })?;
+        features.geometries.push(lines);
+
+        for (key, value) in f.properties_iter() {
+            features
+                .attributes
+                .entry(key.clone())
+                .or_insert_with(|| vec![AttrValue::Null; n])[i] = to_attr(value);
+        }
+    }
+
+    Ok(features)
+}
+
+/// Convert [`LineFeatures`] into a FeatureCollection
+///
+/// Single part geometries are written as `LineString`s.
+pub fn to_feature_collection(features: &LineFeatures) -> FeatureCollection {
+    let features = features
+        .geometries
+        .iter()
+        .enumerate()
+        .map(|(i, lines)| {
+            let properties = features
+                .attributes
+                .iter()
+                .map(|(k, v)| (k.clone(), to_json(&v[i])))
+                .collect::<JsonObject>();
+            Feature {
+                geometry: Some(to_geometry(lines)),
+                properties: Some(properties),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
+
+/// Read [`LineFeatures`] from a GeoJSON FeatureCollection
+pub fn read_geojson(reader: impl Read) -> Result<LineFeatures, AnimeError> {
+    let geojson = GeoJson::from_reader(reader).map_err(io_err)?;
+    let fc = FeatureCollection::try_from(geojson).map_err(io_err)?;
+    from_feature_collection(&fc)
+}
+
+/// Read [`LineFeatures`] from a GeoJSON file
+pub fn read_geojson_file(path: impl AsRef<Path>) -> Result<LineFeatures, AnimeError> {
+    let file = File::open(path).map_err(io_err)?;
+    read_geojson(BufReader::new(file))
+}
+
+/// Write [`LineFeatures`], such as enriched targets, as a GeoJSON FeatureCollection
+pub fn write_geojson(features: &LineFeatures, writer: impl Write) -> Result<(), AnimeError> {
+    let mut writer = BufWriter::new(writer);
+    serde_json::to_writer(&mut writer, &to_feature_collection(features)).map_err(io_err)?;
+    writer.flush().map_err(io_err)
+}
+
+/// Write [`LineFeatures`] to a GeoJSON file
+pub fn write_geojson_file(
+    features: &LineFeatures,
+    path: impl AsRef<Path>,
+) -> Result<(), AnimeError> {
+    let file = File::create(path).map_err(io_err)?;
+    write_geojson(features, file)
+}
+
+/// Convert a match table into a FeatureCollection
+///
+/// Each row is a feature with the geometry of its target.
+pub fn match_table_collection(
+    rows: &[MatchRow],
+    target: &LineFeatures,
+) -> Result<FeatureCollection, AnimeError> {
+    let features = rows
+        .iter()
+        .map(|row| {
+            let lines = target
+                .geometries
+                .get(row.target_id)
+                .ok_or(AnimeError::IncorrectGeometryLength)?;
+            let properties = JsonObject::from_iter([
+                ("target_id".to_string(), JsonValue::from(row.target_id)),
+                ("source_id".to_string(), JsonValue::from(row.source_id)),
+                (
+                    "shared_len".to_string(),
+                    to_json(&AttrValue::Float(row.shared_len)),
+                ),
+                (
+                    "source_weighted".to_string(),
+                    to_json(&AttrValue::Float(row.source_weighted)),
+                ),
+                (
+                    "target_weighted".to_string(),
+                    to_json(&AttrValue::Float(row.target_weighted)),
+                ),
+                (
+                    "confidence".to_string(),
+                    to_json(&AttrValue::Float(row.confidence)),
+                ),
+            ]);
+            Ok(Feature {
+                geometry: Some(to_geometry(lines)),
+                properties: Some(properties),
+                ..Default::default()
+            })
+        })
+        .collect::<Result<Vec<_>, AnimeError>>()?;
+
+    Ok(FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    })
+}
+
+/// Write a match table as a GeoJSON FeatureCollection. See [`match_table_collection()`].
+pub fn write_match_table_geojson(
+    rows: &[MatchRow],
+    target: &LineFeatures,
+    writer: impl Write,
+) -> Result<(), AnimeError> {
+    let fc = match_table_collection(rows, target)?;
+    let mut writer = BufWriter::new(writer);
+    serde_json::to_writer(&mut writer, &fc).map_err(io_err)?;
+    writer.flush().map_err(io_err)
+}
@@ -0,0 +1,243 @@
+//! Reading and writing features in common geospatial formats
+//!
+//! Each format is behind its own cargo feature. All formats read into and
+//! write from [`LineFeatures`], which keeps feature properties as attribute
+//! columns that can be used as interpolation inputs.
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "flatgeobuf")]
+pub mod flatgeobuf;
+#[cfg(feature = "geojson")]
+pub mod geojson;
+#[cfg(feature = "shapefile")]
+pub mod shapefile;
+#[cfg(feature = "wkb")]
+pub mod wkb;
+#[cfg(feature = "wkt")]
+pub mod wkt;
+
+use crate::{
+    interpolate::{InterpolatedValue, Tensive},
+    model::MatchModel,
+    Anime, AnimeError,
+};
+use geo_types::MultiLineString;
+use std::{collections::BTreeMap, fmt::Display};
+
+/// A single attribute value of a feature
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum AttrValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl AttrValue {
+    /// The value as a float if it is numeric
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            AttrValue::Int(v) => Some(*v as f64),
+            AttrValue::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl Display for AttrValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttrValue::Null => Ok(()),
+            AttrValue::Bool(v) => write!(f, "{v}"),
+            AttrValue::Int(v) => write!(f, "{v}"),
+            AttrValue::Float(v) => write!(f, "{v}"),
+            AttrValue::String(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+impl From<Option<f64>> for AttrValue {
+    fn from(value: Option<f64>) -> Self {
+        value.map_or(AttrValue::Null, AttrValue::Float)
+    }
+}
+
+/// The type of a column when written to a typed format
+#[cfg(any(feature = "arrow", feature = "flatgeobuf"))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum AttrType {
+    Bool,
+    Int,
+    Float,
+    String,
+}
+
+/// The narrowest type that can hold every value of a column
+///
+/// Mixed booleans and numbers are written as strings. A column of nulls is
+/// written as floats.
+#[cfg(any(feature = "arrow", feature = "flatgeobuf"))]
+pub(crate) fn attr_type(values: &[AttrValue]) -> AttrType {
+    let has = |f: fn(&AttrValue) -> bool| values.iter().any(f);
+    let is_bool = has(|v| matches!(v, AttrValue::Bool(_)));
+    let is_int = has(|v| matches!(v, AttrValue::Int(_)));
+    let is_float = has(|v| matches!(v, AttrValue::Float(_)));
+    let is_string = has(|v| matches!(v, AttrValue::String(_)));
+
+    if is_string || (is_bool && (is_int || is_float)) {
+        AttrType::String
+    } else if is_float {
+        AttrType::Float
+    } else if is_int {
+        AttrType::Int
+    } else if is_bool {
+        AttrType::Bool
+    } else {
+        AttrType::Float
+    }
+}
+
+/// Convert a reader, writer or format error to [`AnimeError::Io`]
+#[cfg(any(
+    feature = "arrow",
+    feature = "flatgeobuf",
+    feature = "geojson",
+    feature = "serde",
+    feature = "shapefile"
+))]
+pub(crate) fn io_err(e: impl Display) -> AnimeError {
+    AnimeError::Io(e.to_string())
+}
+
+/// Convert the geometry of feature `i` to a `MultiLineString`
+#[cfg(any(
+    feature = "arrow",
+    feature = "flatgeobuf",
+    feature = "wkb",
+    feature = "wkt"
+))]
+pub(crate) fn to_lines(i: usize, geom: geo_types::Geometry) -> Result<MultiLineString, AnimeError> {
+    match geom {
+        geo_types::Geometry::LineString(l) => Ok(MultiLineString::new(vec![l])),
+        geo_types::Geometry::MultiLineString(l) => Ok(l),
+        _ => Err(AnimeError::UnsupportedGeometry {
+            side: None,
+            index: i,
+        }),
+    }
+}
+
+/// Line geometries and their attribute columns
+///
+/// Each column has one value per geometry. Features that did not have a
+/// property have [`AttrValue::Null`] in its column.
+#[derive(Debug, Clone, Default)]
+pub struct LineFeatures {
+    pub geometries: Vec<MultiLineString>,
+    pub attributes: BTreeMap<String, Vec<AttrValue>>,
+}
+
+impl LineFeatures {
+    /// Number of features
+    pub fn len(&self) -> usize {
+        self.geometries.len()
+    }
+
+    /// Returns `true` if there are no features
+    pub fn is_empty(&self) -> bool {
+        self.geometries.is_empty()
+    }
+
+    /// The values of a column
+    pub fn column(&self, name: &str) -> Option<&[AttrValue]> {
+        self.attributes.get(name).map(|v| v.as_slice())
+    }
+
+    /// The values of a column as floats
+    ///
+    /// Null and non-numeric values are `NaN`.
+    pub fn numeric_column(&self, name: &str) -> Option<Vec<f64>> {
+        let col = self.column(name)?;
+        Some(col.iter().map(|v| v.as_f64().unwrap_or(f64::NAN)).collect())
+    }
+
+    /// Add or replace a column
+    pub fn set_column(
+        &mut self,
+        name: impl Into<String>,
+        values: Vec<AttrValue>,
+    ) -> Result<(), AnimeError> {
+        if values.len() != self.len() {
+            return Err(AnimeError::IncorrectLength);
+        }
+        self.attributes.insert(name.into(), values);
+        Ok(())
+    }
+}
+
+impl Anime {
+    /// Load source and target features and find matches
+    pub fn from_features(
+        source: &LineFeatures,
+        target: &LineFeatures,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+    ) -> Self {
+        Anime::new(
+            source.geometries.iter().cloned(),
+            target.geometries.iter().cloned(),
+            distance_tolerance,
+            angle_tolerance,
+        )
+    }
+
+    /// Interpolate a numeric column of the source features onto the targets
+    ///
+    /// Returns one value per target. Targets without a match, or whose
+    /// interpolated value is not finite, are [`AttrValue::Null`].
+    pub fn interpolate_column(
+        &self,
+        source: &LineFeatures,
+        column: &str,
+        tensive: Tensive,
+    ) -> Result<Vec<AttrValue>, AnimeError> {
+        interpolate_column(source, column, self.target_lens.len(), |var| {
+            self.interpolate(var, tensive)
+        })
+    }
+}
+
+impl MatchModel {
+    /// Interpolate a numeric column of the source features onto the targets.
+    /// See [`Anime::interpolate_column()`].
+    pub fn interpolate_column(
+        &self,
+        source: &LineFeatures,
+        column: &str,
+        tensive: Tensive,
+    ) -> Result<Vec<AttrValue>, AnimeError> {
+        interpolate_column(source, column, self.target_lens.len(), |var| {
+            self.interpolate(var, tensive)
+        })
+    }
+}
+
+fn interpolate_column(
+    source: &LineFeatures,
+    column: &str,
+    n_targets: usize,
+    interpolate: impl FnOnce(&[f64]) -> Result<Vec<InterpolatedValue>, AnimeError>,
+) -> Result<Vec<AttrValue>, AnimeError> {
+    let var = source
+        .numeric_column(column)
+        .ok_or_else(|| AnimeError::ColumnNotFound(column.to_string()))?;
+    let mut values = vec![AttrValue::Null; n_targets];
+    for v in interpolate(&var)? {
+        if v.value.is_finite() {
+            values[v.target_id] = AttrValue::Float(v.value);
+        }
+    }
+    Ok(values)
+}
@@ -0,0 +1,183 @@
+//! Shapefiles of `PolyLine`, `PolyLineM` or `PolyLineZ` records and their DBF attributes
+use super::{io_err, AttrValue, LineFeatures};
+use crate::AnimeError;
+use geo_types::{LineString, MultiLineString};
+use shapefile::{
+    dbase::{self, FieldValue},
+    Reader, Shape, ShapeReader,
+};
+use std::{
+    io::{Read, Seek},
+    path::Path,
+};
+
+fn to_attr(value: FieldValue) -> AttrValue {
+    match value {
+        FieldValue::Character(v) => v.map_or(AttrValue::Null, AttrValue::String),
+        FieldValue::Memo(v) => AttrValue::String(v),
+        FieldValue::Numeric(v) => v.into(),
+        FieldValue::Float(v) => v.map(|v| v as f64).into(),
+        FieldValue::Double(v) | FieldValue::Currency(v) => AttrValue::Float(v),
+        FieldValue::Integer(v) => AttrValue::Int(v as i64),
+        FieldValue::Logical(v) => v.map_or(AttrValue::Null, AttrValue::Bool),
+        FieldValue::Date(v) => v.map_or(AttrValue::Null, |d| AttrValue::String(d.to_string())),
+        // date times are not interpolated
+        FieldValue::DateTime(_) => AttrValue::Null,
+    }
+}
+
+/// Convert the shape of record `i` to a `MultiLineString`
+///
+/// Measures and z values are dropped.
+fn to_lines(i: usize, shape: Shape) -> Result<MultiLineString, AnimeError> {
+    match shape {
+        Shape::Polyline(l) => Ok(from_parts(l.parts(), |p| (p.x, p.y))),
+        Shape::PolylineM(l) => Ok(from_parts(l.parts(), |p| (p.x, p.y))),
+        Shape::PolylineZ(l) => Ok(from_parts(l.parts(), |p| (p.x, p.y))),
+        _ => Err(AnimeError::UnsupportedGeometry {
+            side: None,
+            index: i,
+        }),
+    }
+}
+
+fn from_parts<P>(parts: &[Vec<P>], xy: impl Fn(&P) -> (f64, f64)) -> MultiLineString {
+    parts
+        .iter()
+        .map(|part| LineString::from(part.iter().map(&xy).collect::<Vec<_>>()))
+        .collect()
+}
+
+fn read_records<T: Read + Seek, D: Read + Seek>(
+    mut reader: Reader<T, D>,
+) -> Result<LineFeatures, AnimeError> {
+    let records = reader.read().map_err(io_err)?;
+    let n = records.len();
+    let mut features = LineFeatures::default();
+
+    for (i, (shape, record)) in records.into_iter().enumerate() {
+        features.geometries.push(to_lines(i, shape)?);
+        for (name, value) in record {
+            features
+                .attributes
+                .entry(name)
+                .or_insert_with(|| vec![AttrValue::Null; n])[i] = to_attr(value);
+        }
+    }
+
+    Ok(features)
+}
+
+/// Read [`LineFeatures`] from the `.shp` and `.dbf` parts of a Shapefile
+///
+/// Every record must be a `PolyLine`, `PolyLineM` or `PolyLineZ`. DBF
+/// numeric fields can be used as interpolation inputs.
+pub fn read_shapefile(
+    shp: impl Read + Seek,
+    dbf: impl Read + Seek,
+) -> Result<LineFeatures, AnimeError> {
+    let shape_reader = ShapeReader::new(shp).map_err(io_err)?;
+    let dbase_reader = dbase::Reader::new(dbf).map_err(io_err)?;
+    read_records(Reader::new(shape_reader, dbase_reader))
+}
+
+/// Read [`LineFeatures`] from a Shapefile. See [`read_shapefile()`].
+///
+/// The `.dbf` file next to the `.shp` file is required.
+pub fn read_shapefile_file(path: impl AsRef<Path>) -> Result<LineFeatures, AnimeError> {
+    read_records(Reader::from_path(path).map_err(io_err)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+    use shapefile::{
+        dbase::{Record, TableWriterBuilder},
+        Point, Polyline, ShapeWriter, Writer,
+    };
+    use std::io::Cursor;
+
+    /// Write two polylines, the second with two parts, and their records
+    fn shapefile() -> (Vec<u8>, Vec<u8>) {
+        let mut shp = Cursor::new(Vec::new());
+        let mut shx = Cursor::new(Vec::new());
+        let mut dbf = Cursor::new(Vec::new());
+        {
+            let shape_writer = ShapeWriter::with_shx(&mut shp, &mut shx);
+            let field = |name: &str| name.try_into().unwrap();
+            let dbase_writer = TableWriterBuilder::new()
+                .add_character_field(field("NAME"), 20)
+                .add_numeric_field(field("LANES"), 10, 0)
+                .add_float_field(field("SPEED"), 10, 2)
+                .add_logical_field(field("ONEWAY"))
+                .build_with_dest(&mut dbf);
+            let mut writer = Writer::new(shape_writer, dbase_writer);
+
+            let line = |coords: &[(f64, f64)]| {
+                coords
+                    .iter()
+                    .map(|(x, y)| Point::new(*x, *y))
+                    .collect::<Vec<_>>()
+            };
+            let shapes = [
+                Polyline::new(line(&[(0.0, 0.0), (10.0, 0.0)])),
+                Polyline::with_parts(vec![
+                    line(&[(0.0, 5.0), (10.0, 5.0)]),
+                    line(&[(10.0, 5.0), (10.0, 15.0)]),
+                ]),
+            ];
+            let records = [
+                (Some("Main St"), Some(2.0), Some(30.5), Some(true)),
+                (None, None, Some(50.0), Some(false)),
+            ]
+            .map(|(name, lanes, speed, oneway)| {
+                let mut record = Record::default();
+                let name = name.map(str::to_string);
+                record.insert("NAME".to_string(), FieldValue::Character(name));
+                record.insert("LANES".to_string(), FieldValue::Numeric(lanes));
+                record.insert("SPEED".to_string(), FieldValue::Float(speed));
+                record.insert("ONEWAY".to_string(), FieldValue::Logical(oneway));
+                record
+            });
+            for (shape, record) in shapes.iter().zip(&records) {
+                writer.write_shape_and_record(shape, record).unwrap();
+            }
+        }
+        (shp.into_inner(), dbf.into_inner())
+    }
+
+    #[test]
+    fn polylines_and_dbf_fields_are_read() {
+        let (shp, dbf) = shapefile();
+        let features = read_shapefile(Cursor::new(shp), Cursor::new(dbf)).unwrap();
+
+        assert_eq!(
+            features.geometries,
+            vec![
+                MultiLineString::new(vec![line_string![(x: 0.0, y: 0.0), (x: 10.0, y: 0.0)]]),
+                MultiLineString::new(vec![
+                    line_string![(x: 0.0, y: 5.0), (x: 10.0, y: 5.0)],
+                    line_string![(x: 10.0, y: 5.0), (x: 10.0, y: 15.0)],
+                ]),
+            ]
+        );
+        let column = |name: &str| features.attributes[name].clone();
+        assert_eq!(
+            column("NAME"),
+            vec![AttrValue::String("Main St".to_string()), AttrValue::Null]
+        );
+        assert_eq!(
+            column("LANES"),
+            vec![AttrValue::Float(2.0), AttrValue::Null]
+        );
+        assert_eq!(
+            column("SPEED"),
+            vec![AttrValue::Float(30.5), AttrValue::Float(50.0)]
+        );
+        assert_eq!(
+            column("ONEWAY"),
+            vec![AttrValue::Bool(true), AttrValue::Bool(false)]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Well-known binary line geometries
+use super::to_lines;
+use crate::{Anime, AnimeError, Side};
+use geo_traits::to_geo::ToGeoGeometry;
+use geo_types::MultiLineString;
+
+/// Parse the WKB of feature `index` into a `MultiLineString`
+///
+/// Both ISO WKB and PostGIS EWKB are supported. An embedded SRID is ignored.
+pub fn parse_wkb(index: usize, wkb: &[u8]) -> Result<MultiLineString, AnimeError> {
+    let geom = wkb::reader::read_wkb(wkb).map_err(|e| AnimeError::Parse {
+        side: None,
+        index,
+        message: e.to_string(),
+    })?;
+    to_lines(index, geom.to_geometry())
+}
+
+fn parse_all(
+    wkb: impl Iterator<Item = impl AsRef<[u8]>>,
+) -> Result<Vec<MultiLineString>, AnimeError> {
+    wkb.enumerate()
+        .map(|(i, w)| parse_wkb(i, w.as_ref()))
+        .collect()
+}
+
+impl Anime {
+    /// Parse source and target WKB `LineString`s or `MultiLineString`s and find matches
+    ///
+    /// Sources are parsed before targets. The first feature that can't be
+    /// parsed is reported with its side and index in [`AnimeError::Parse`].
+    pub fn from_wkb(
+        source: impl Iterator<Item = impl AsRef<[u8]>>,
+        target: impl Iterator<Item = impl AsRef<[u8]>>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+    ) -> Result<Self, AnimeError> {
+        let source = parse_all(source).map_err(|e| e.with_side(Side::Source))?;
+        let target = parse_all(target).map_err(|e| e.with_side(Side::Target))?;
+        Ok(Anime::new(
+            source.into_iter(),
+            target.into_iter(),
+            distance_tolerance,
+            angle_tolerance,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+
+    /// Little endian WKB of a `LineString` with an optional EWKB SRID
+    fn line_wkb(coords: &[(f64, f64)], srid: Option<u32>) -> Vec<u8> {
+        let mut wkb = vec![1];
+        match srid {
+            Some(srid) => {
+                wkb.extend((2u32 | 0x2000_0000).to_le_bytes());
+                wkb.extend(srid.to_le_bytes());
+            }
+            None => wkb.extend(2u32.to_le_bytes()),
+        }
+        wkb.extend((coords.len() as u32).to_le_bytes());
+        for (x, y) in coords {
+            wkb.extend(x.to_le_bytes());
+            wkb.extend(y.to_le_bytes());
+        }
+        wkb
+    }
+
+    #[test]
+    fn iso_and_ewkb_lines_are_parsed() {
+        let coords = [(0.0, 0.0), (10.0, 0.0), (10.0, 5.0)];
+        let expected = MultiLineString::new(vec![line_string![
+            (x: 0.0, y: 0.0),
+            (x: 10.0, y: 0.0),
+            (x: 10.0, y: 5.0),
+        ]]);
+        assert_eq!(parse_wkb(0, &line_wkb(&coords, None)).unwrap(), expected);
+        assert_eq!(
+            parse_wkb(0, &line_wkb(&coords, Some(27700))).unwrap(),
+            expected
+        );
+
+        // a MultiLineString of the same line twice
+        let mut multi = vec![1];
+        multi.extend(5u32.to_le_bytes());
+        multi.extend(2u32.to_le_bytes());
+        multi.extend(line_wkb(&coords, None));
+        multi.extend(line_wkb(&coords, None));
+        assert_eq!(parse_wkb(0, &multi).unwrap().0.len(), 2);
+    }
+
+    #[test]
+    fn invalid_wkb_names_the_side() {
+        let line = line_wkb(&[(0.0, 0.0), (10.0, 0.0)], Some(4326));
+        let truncated = &line[..line.len() - 4];
+        let err = Anime::from_wkb(
+            [line.as_slice()].into_iter(),
+            [truncated].into_iter(),
+            1.0,
+            5.0,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            AnimeError::Parse {
+                side: Some(Side::Target),
+                index: 0,
+                ..
+            }
+        ));
+
+        let mut point = vec![1];
+        point.extend(1u32.to_le_bytes());
+        point.extend(0.0f64.to_le_bytes());
+        point.extend(0.0f64.to_le_bytes());
+        let err = Anime::from_wkb([point].iter(), [line].iter(), 1.0, 5.0).unwrap_err();
+        assert!(matches!(
+            err,
+            AnimeError::UnsupportedGeometry {
+                side: Some(Side::Source),
+                index: 0,
+            }
+        ));
+    }
+}
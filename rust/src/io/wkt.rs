@@ -0,0 +1,75 @@
+//! Well-known text line geometries
+use super::to_lines;
+use crate::{Anime, AnimeError, Side};
+use geo_types::{Geometry, MultiLineString};
+use wkt::TryFromWkt;
+
+/// Parse the WKT of feature `index` into a `MultiLineString`
+///
+/// A leading EWKT `SRID=<srid>;` prefix is ignored.
+pub fn parse_wkt(index: usize, wkt: &str) -> Result<MultiLineString, AnimeError> {
+    let wkt = wkt.trim();
+    let wkt = match wkt.split_once(';') {
+        Some((srid, rest)) if srid.trim_start().to_ascii_uppercase().starts_with("SRID=") => rest,
+        _ => wkt,
+    };
+    let geom = Geometry::try_from_wkt_str(wkt).map_err(|e| AnimeError::Parse {
+        side: None,
+        index,
+        message: e.to_string(),
+    })?;
+    to_lines(index, geom)
+}
+
+fn parse_all(
+    wkt: impl Iterator<Item = impl AsRef<str>>,
+) -> Result<Vec<MultiLineString>, AnimeError> {
+    wkt.enumerate()
+        .map(|(i, w)| parse_wkt(i, w.as_ref()))
+        .collect()
+}
+
+impl Anime {
+    /// Parse source and target WKT `LineString`s or `MultiLineString`s and find matches
+    ///
+    /// Sources are parsed before targets. The first feature that can't be
+    /// parsed is reported with its side and index in [`AnimeError::Parse`].
+    pub fn from_wkt(
+        source: impl Iterator<Item = impl AsRef<str>>,
+        target: impl Iterator<Item = impl AsRef<str>>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+    ) -> Result<Self, AnimeError> {
+        let source = parse_all(source).map_err(|e| e.with_side(Side::Source))?;
+        let target = parse_all(target).map_err(|e| e.with_side(Side::Target))?;
+        Ok(Anime::new(
+            source.into_iter(),
+            target.into_iter(),
+            distance_tolerance,
+            angle_tolerance,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_errors_name_the_side() {
+        let source = ["LINESTRING (0 0, 1 0)"];
+        let target = ["LINESTRING (0 0, 1 0)", "LINESTRING (0 0,"];
+        let err = Anime::from_wkt(source.iter(), target.iter(), 1.0, 5.0).unwrap_err();
+        assert!(matches!(
+            err,
+            AnimeError::Parse {
+                side: Some(Side::Target),
+                index: 1,
+                ..
+            }
+        ));
+        assert!(err
+            .to_string()
+            .starts_with("Failed to parse target feature 1"));
+    }
+}
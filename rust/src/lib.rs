@@ -1,35 +1,140 @@
+pub mod builder;
+pub mod cardinality;
+pub mod change;
+pub mod conflate;
+pub mod constraint;
+pub mod coverage;
+pub mod descriptor;
+pub mod duplicates;
 pub mod interpolate;
+pub mod io;
+mod linear;
+pub mod model;
+pub mod multi;
+pub mod names;
 mod overlap;
+#[cfg(feature = "serde")]
+pub mod persist;
+pub mod preprocess;
+pub mod rubbersheet;
+pub mod shift;
+pub mod sparse;
 pub mod structs;
+pub mod table;
+pub mod tolerance;
+pub mod validate;
 
 use crate::{
-    overlap::*, overlap_range, solve_no_x_overlap, solve_no_y_overlap, structs::*, x_range,
-    y_range, TarLine,
+    builder::{CrsMode, DirectionMode, MatchOptions, OverlapMethod},
+    constraint::MatchConstraint,
+    descriptor::LineDescriptor,
+    overlap::*,
+    overlap_range, solve_no_x_overlap, solve_no_y_overlap,
+    structs::*,
+    tolerance::FeatureTolerances,
+    x_range, y_range, TarLine,
 };
-use geo::{BoundingRect, Distance, Euclidean, Length};
+use geo::{BoundingRect, Distance, Euclidean, Length, LinesIter};
 use rstar::primitives::{CachedEnvelope, GeomWithData};
-use std::{cell::OnceCell, collections::BTreeMap, error::Error, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+    sync::OnceLock,
+};
+
+/// Which of the two datasets of a match a feature belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Source,
+    Target,
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::Source => write!(f, "source"),
+            Side::Target => write!(f, "target"),
+        }
+    }
+}
 
 /// Anime Error Type
 #[derive(Debug, Clone)]
 pub enum AnimeError {
     IncorrectLength,
+    IncorrectGeometryLength,
+    IncorrectToleranceLength,
+    InsufficientCorrespondences,
     MatchesNotFound,
     AlreadyMatched(MatchesMap),
+    ColumnNotFound(String),
+    /// `side` is `None` when the feature was read on its own rather than as
+    /// part of a source or target dataset
+    UnsupportedGeometry {
+        side: Option<Side>,
+        index: usize,
+    },
+    Io(String),
+    /// `side` is `None` when the feature was read on its own rather than as
+    /// part of a source or target dataset
+    Parse {
+        side: Option<Side>,
+        index: usize,
+        message: String,
+    },
+    InvalidTolerance(String),
+    InvalidOption(String),
 }
 
 impl Display for AnimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             AnimeError::IncorrectLength => write!(f, "Variable to interpolate must have the same number of observations as the `target` lines"),
+            AnimeError::IncorrectGeometryLength => write!(f, "Geometries must have the same number of observations as the `source` and `target` lines"),
+            AnimeError::IncorrectToleranceLength => write!(f, "Per feature tolerances must have the same number of observations as the `source` or `target` lines"),
+            AnimeError::InsufficientCorrespondences => write!(f, "Not enough correspondences between `source` and `target` to estimate a shift"),
             AnimeError::MatchesNotFound => write!(f, "`matches` needs to be instantiated with `self.find_matches()`"),
-            AnimeError::AlreadyMatched(_) => write!(f, "matches already found.")
+            AnimeError::AlreadyMatched(_) => write!(f, "matches already found."),
+            AnimeError::ColumnNotFound(name) => write!(f, "Column `{name}` not found"),
+            AnimeError::UnsupportedGeometry { side, index } => write!(f, "{} is not a `LineString` or `MultiLineString`", feature_label(side, *index)),
+            AnimeError::Io(msg) => write!(f, "Failed to read or write features: {msg}"),
+            AnimeError::Parse { side, index, message } => write!(f, "Failed to parse {}: {message}", feature_label(side, *index)),
+            AnimeError::InvalidTolerance(msg) => write!(f, "Invalid tolerance: {msg}"),
+            AnimeError::InvalidOption(msg) => write!(f, "Invalid option: {msg}"),
         }
     }
 }
 
 impl Error for AnimeError {}
 
+fn feature_label(side: &Option<Side>, index: usize) -> String {
+    match side {
+        Some(side) => format!("{side} feature {index}"),
+        None => format!("feature {index}"),
+    }
+}
+
+impl AnimeError {
+    /// Attribute a geometry or parse error to the source or target dataset
+    ///
+    /// Other errors are returned unchanged.
+    pub fn with_side(self, side: Side) -> Self {
+        match self {
+            AnimeError::UnsupportedGeometry { index, .. } => AnimeError::UnsupportedGeometry {
+                side: Some(side),
+                index,
+            },
+            AnimeError::Parse { index, message, .. } => AnimeError::Parse {
+                side: Some(side),
+                index,
+                message,
+            },
+            e => e,
+        }
+    }
+}
+
 /// R* Tree for source geometries
 pub type SourceTree = rstar::RTree<GeomWithData<CachedEnvelope<geo_types::Line>, (usize, f64)>>;
 
@@ -38,11 +143,42 @@ pub type TargetTree = rstar::RTree<GeomWithData<CachedEnvelope<TarLine>, (usize,
 
 /// Represents a partial source <-> target match
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MatchCandidate {
     /// The index of the source geometry
     pub source_index: usize,
     /// The amount of shared length between two geometries
     pub shared_len: f64,
+    /// Attribute compatibility weight of the pair in the range `[0, 1]`
+    ///
+    /// This is `1.0` unless the matches were found with a [`MatchConstraint`].
+    pub confidence: f64,
+    /// Shape distance between the matched portions of the two geometries
+    ///
+    /// This is `None` unless the matches were validated with
+    /// [`Anime::validate_shapes()`].
+    pub shape_distance: Option<f64>,
+}
+
+#[cfg(test)]
+pub(crate) fn candidate(source_index: usize, shared_len: f64) -> MatchCandidate {
+    MatchCandidate {
+        source_index,
+        shared_len,
+        confidence: 1.0,
+        shape_distance: None,
+    }
+}
+
+/// A straight chain of `n` connected 10 unit segments along `y = offset`
+#[cfg(test)]
+pub(crate) fn chain(n: usize, offset: f64) -> Vec<geo_types::LineString> {
+    (0..n)
+        .map(|k| {
+            let x = k as f64 * 10.0;
+            geo_types::LineString::from(vec![(x, offset), (x + 10.0, offset)])
+        })
+        .collect()
 }
 
 /// Stores match length
@@ -63,86 +199,141 @@ pub type MatchesMap = BTreeMap<usize, Vec<MatchCandidate>>;
 ///
 /// The lengths, represented as `Vec<f64>` are required for the
 /// integration of attributes.
+///
+/// The scalar tolerances can be overridden per feature with
+/// `feature_tolerances`. Shape descriptors of each feature are only
+/// present when loaded with [`Anime::load_geometries_with_descriptors()`]
+/// or [`builder::AnimeBuilder::descriptors()`].
+///
+/// `Anime` is `Send + Sync` so matches can be shared between threads, for
+/// example in an `Arc`, once they are found.
 #[derive(Clone, Debug)]
 pub struct Anime {
     pub distance_tolerance: f64,
     pub angle_tolerance: f64,
+    pub feature_tolerances: FeatureTolerances,
+    /// How lines are compared and the CRS the trees are in
+    pub options: MatchOptions,
     pub source_tree: SourceTree,
     pub source_lens: Vec<f64>,
+    pub source_descriptors: Option<Vec<LineDescriptor>>,
     pub target_tree: TargetTree,
     pub target_lens: Vec<f64>,
-    pub matches: OnceCell<MatchesMap>,
+    pub target_descriptors: Option<Vec<LineDescriptor>>,
+    pub matches: OnceLock<MatchesMap>,
 }
 
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Anime>();
+};
+
 impl Anime {
     /// Load source and target `LineString` geometries
     ///
     /// This creates two R* Trees using cached envelopes for each component
     /// line in a LineString. In addition to the envelope, the slope and
     /// index of the LineString is stored.
+    ///
+    /// `MultiLineString`s are also accepted. Each is treated as a single
+    /// feature whose length is the sum of its parts.
     pub fn load_geometries(
-        source: impl Iterator<Item = geo_types::LineString>,
-        target: impl Iterator<Item = geo_types::LineString>,
+        source: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
+        target: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
         distance_tolerance: f64,
         angle_tolerance: f64,
     ) -> Self {
         let mut source_lens = Vec::new();
         let mut target_lens = Vec::new();
         let source_tree = create_source_rtree(source, &mut source_lens);
-        let target_tree = create_target_rtree(target, &mut target_lens, distance_tolerance);
+        let target_tree = create_target_rtree(target, &mut target_lens, |_| distance_tolerance);
+        Self::from_trees(
+            distance_tolerance,
+            angle_tolerance,
+            FeatureTolerances::default(),
+            MatchOptions::default(),
+            (source_tree, source_lens),
+            (target_tree, target_lens),
+        )
+    }
+
+    /// Assemble an `Anime` from indexed geometries and their lengths
+    ///
+    /// Every constructor goes through here. Descriptors and matches are not set.
+    pub(crate) fn from_trees(
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+        feature_tolerances: FeatureTolerances,
+        options: MatchOptions,
+        (source_tree, source_lens): (SourceTree, Vec<f64>),
+        (target_tree, target_lens): (TargetTree, Vec<f64>),
+    ) -> Self {
         Self {
             distance_tolerance,
             angle_tolerance,
+            feature_tolerances,
+            options,
             source_tree,
             source_lens,
+            source_descriptors: None,
             target_tree,
             target_lens,
-            matches: OnceCell::new(),
+            target_descriptors: None,
+            matches: OnceLock::new(),
         }
     }
 
+    /// Refuse an operation on geometries in the CRS of the inputs
+    ///
+    /// With [`CrsMode::Geographic`] the trees are in a projection of the
+    /// longitude and latitude inputs, so they can't be compared with them.
+    pub(crate) fn require_projected(&self, operation: &str) -> Result<(), AnimeError> {
+        if self.options.crs_mode == CrsMode::Geographic {
+            return Err(AnimeError::InvalidOption(format!(
+                "`{operation}` needs projected geometries but the matches were found with `CrsMode::Geographic`"
+            )));
+        }
+        Ok(())
+    }
+
     /// Find candidate matches between source and target
     ///
     /// The matches can only be found once for each source and target pair.
+    /// Lines are compared with the overlap and direction modes of `options`.
     pub fn find_matches(&mut self) -> Result<&mut Anime, AnimeError> {
-        let matches = find_candidate_matches(
+        let matches = find_candidate_matches_with(
             &self.source_tree,
             &self.target_tree,
             self.angle_tolerance,
             self.distance_tolerance,
+            &self.feature_tolerances,
+            None,
+            self.options.pair_options(),
         );
         self.matches
             .set(matches)
-            .map_err(|e| AnimeError::AlreadyMatched(e))?;
+            .map_err(AnimeError::AlreadyMatched)?;
         Ok(self)
     }
 
     /// Insert linestring geometries and find matches
     pub fn new(
-        source: impl Iterator<Item = geo_types::LineString>,
-        target: impl Iterator<Item = geo_types::LineString>,
+        source: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
+        target: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
         distance_tolerance: f64,
         angle_tolerance: f64,
     ) -> Self {
-        let mut source_lens = Vec::new();
-        let mut target_lens = Vec::new();
-        let source_tree = create_source_rtree(source, &mut source_lens);
-        let target_tree = create_target_rtree(target, &mut target_lens, distance_tolerance);
+        let mut anime = Self::load_geometries(source, target, distance_tolerance, angle_tolerance);
         let matches = find_candidate_matches(
-            &source_tree,
-            &target_tree,
+            &anime.source_tree,
+            &anime.target_tree,
             angle_tolerance,
             distance_tolerance,
+            &anime.feature_tolerances,
+            None,
         );
-        Self {
-            distance_tolerance,
-            angle_tolerance,
-            source_tree,
-            source_lens,
-            target_tree,
-            target_lens,
-            matches: OnceCell::from(matches),
-        }
+        anime.matches = OnceLock::from(matches);
+        anime
     }
 }
 fn find_candidate_matches(
@@ -150,87 +341,182 @@ fn find_candidate_matches(
     target_tree: &TargetTree,
     angle_tolerance: f64,
     distance_tolerance: f64,
+    feature_tolerances: &FeatureTolerances,
+    constraint: Option<&dyn MatchConstraint>,
+) -> MatchesMap {
+    find_candidate_matches_with(
+        source_tree,
+        target_tree,
+        angle_tolerance,
+        distance_tolerance,
+        feature_tolerances,
+        constraint,
+        PairOptions::default(),
+    )
+}
+
+/// Find candidate matches with non-default overlap and direction handling
+pub(crate) fn find_candidate_matches_with(
+    source_tree: &SourceTree,
+    target_tree: &TargetTree,
+    angle_tolerance: f64,
+    distance_tolerance: f64,
+    feature_tolerances: &FeatureTolerances,
+    constraint: Option<&dyn MatchConstraint>,
+    options: PairOptions,
 ) -> MatchesMap {
     let mut matches: MatchesMap = BTreeMap::new();
+    // constraint weights are evaluated once per (source, target) pair
+    let mut weights: HashMap<(usize, usize), f64> = HashMap::new();
     let candidates = source_tree.intersection_candidates_with_other_tree(target_tree);
 
     candidates.for_each(|(cx, cy)| {
-        let xbb = cx.geom().bounding_rect();
-        let ybb = cy.geom().0.bounding_rect();
-
         // extract cached slopes and index positions
         let (i, x_slope) = cx.data;
         let (j, y_slope) = cy.data;
 
-        // convert calculated slopes to degrees
-        let x_deg = x_slope.atan().to_degrees();
-        let y_deg = y_slope.atan().to_degrees();
-
-        // compare slopes:
-        let is_tolerant = (x_deg - y_deg).abs() < angle_tolerance;
-
-        // if the slopes are within tolerance then we check for overlap
-        if is_tolerant {
-            let xx_range = x_range(&xbb);
-            let xy_range = x_range(&ybb);
-            let x_overlap = overlap_range(xx_range, xy_range);
-            let y_overlap = overlap_range(y_range(&xbb), y_range(&ybb));
-
-            // if theres overlap then we do a distance based check
-            // following, check that they're within distance tolerance,
-            // if so, calculate the shared length
-            if x_overlap.is_some() || y_overlap.is_some() {
-                // calculate the distance from the line segment
-                // if its within our threshold we include it;
-                let d = cy.geom().distance(cx.geom());
-
-                // if distance is less than or equal to tolerance, add the key
-                if d <= distance_tolerance {
-                    let shared_len = if x_slope.atan().to_degrees() <= 45.0 {
-                        if x_overlap.is_some() {
-                            let (p1, p2) =
-                                solve_no_y_overlap(x_overlap.unwrap(), cx.geom(), &x_slope);
-
-                            Euclidean::distance(&p1, &p2)
-                        } else {
-                            0.0
-                        }
-                    } else if y_overlap.is_some() {
-                        let (p1, p2) = solve_no_x_overlap(y_overlap.unwrap(), cx.geom(), &x_slope);
-                        Euclidean::distance(&p1, &p2)
-                    } else {
-                        0.0
-                    };
-                    // add 1 for R indexing
-                    // ensures that no duplicates are inserted. Creates a new empty vector is needed
-                    let entry = matches.entry(j).or_default();
-
-                    if let Some(tuple) = entry.iter_mut().find(|x| x.source_index == i) {
-                        tuple.shared_len += shared_len;
-                    } else {
-                        entry.push(MatchCandidate {
-                            source_index: i,
-                            shared_len,
-                        });
-                    }
-                }
+        // per feature tolerances fall back to the scalar tolerances
+        let angle_tolerance = feature_tolerances.angle(i, j, angle_tolerance);
+        let distance_tolerance = feature_tolerances.distance(i, j, distance_tolerance);
+
+        // check attribute compatibility before the more costly computations
+        let confidence = match constraint {
+            Some(c) => *weights.entry((i, j)).or_insert_with(|| c.weight(i, j)),
+            None => 1.0,
+        };
+
+        if confidence > 0.0 {
+            let shared_len = pair_shared_len(
+                cx.geom(),
+                x_slope,
+                cy.geom(),
+                y_slope,
+                angle_tolerance,
+                distance_tolerance,
+                options,
+            );
+            if let Some(shared_len) = shared_len {
+                add_candidate(&mut matches, i, j, shared_len, confidence);
             }
         }
     });
     matches
 }
 
+/// How a source and target component line are compared
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PairOptions {
+    pub overlap_method: OverlapMethod,
+    pub direction_mode: DirectionMode,
+}
+
+/// The shared length of a source and target component line
+///
+/// Returns `None` if the lines are not within the angle and distance
+/// tolerances.
+pub(crate) fn pair_shared_len(
+    source: &geo_types::Line,
+    x_slope: f64,
+    target: &TarLine,
+    y_slope: f64,
+    angle_tolerance: f64,
+    distance_tolerance: f64,
+    options: PairOptions,
+) -> Option<f64> {
+    // convert calculated slopes to degrees
+    let x_deg = x_slope.atan().to_degrees();
+    let y_deg = y_slope.atan().to_degrees();
+
+    // compare slopes:
+    let is_tolerant = (x_deg - y_deg).abs() < angle_tolerance;
+    if !is_tolerant {
+        return None;
+    }
+
+    // directed lines must also point the same way
+    if options.direction_mode == DirectionMode::Directed && !same_direction(source, &target.0) {
+        return None;
+    }
+
+    // if the slopes are within tolerance then we check for overlap
+    let xbb = source.bounding_rect();
+    let ybb = target.0.bounding_rect();
+    let xx_range = x_range(&xbb);
+    let xy_range = x_range(&ybb);
+    let x_overlap = overlap_range(xx_range, xy_range);
+    let y_overlap = overlap_range(y_range(&xbb), y_range(&ybb));
+
+    // if theres overlap then we do a distance based check
+    // following, check that they're within distance tolerance,
+    // if so, calculate the shared length
+    if x_overlap.is_none() && y_overlap.is_none() {
+        return None;
+    }
+
+    // calculate the distance from the line segment
+    // if its within our threshold we include it;
+    let d = target.distance(source);
+    if d > distance_tolerance {
+        return None;
+    }
+
+    let shared_len = match options.overlap_method {
+        OverlapMethod::Axis => {
+            if x_slope.atan().to_degrees() <= 45.0 {
+                if let Some(x_overlap) = x_overlap {
+                    let (p1, p2) = solve_no_y_overlap(x_overlap, source, &x_slope);
+
+                    Euclidean::distance(&p1, &p2)
+                } else {
+                    0.0
+                }
+            } else if let Some(y_overlap) = y_overlap {
+                let (p1, p2) = solve_no_x_overlap(y_overlap, source, &x_slope);
+                Euclidean::distance(&p1, &p2)
+            } else {
+                0.0
+            }
+        }
+        OverlapMethod::Projection => projected_overlap(source, &target.0),
+    };
+    Some(shared_len)
+}
+
+/// Add the shared length of a source and target pair to the matches
+pub(crate) fn add_candidate(
+    matches: &mut MatchesMap,
+    i: usize,
+    j: usize,
+    shared_len: f64,
+    confidence: f64,
+) {
+    // ensures that no duplicates are inserted. Creates a new empty vector is needed
+    let entry = matches.entry(j).or_default();
+
+    if let Some(tuple) = entry.iter_mut().find(|x| x.source_index == i) {
+        tuple.shared_len += shared_len;
+    } else {
+        entry.push(MatchCandidate {
+            source_index: i,
+            shared_len,
+            confidence,
+            shape_distance: None,
+        });
+    }
+}
+
 fn create_source_rtree(
-    x: impl Iterator<Item = geo_types::LineString>,
+    x: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
     source_lens: &mut Vec<f64>,
 ) -> SourceTree {
     let to_insert = x
         .enumerate()
         .flat_map(|(i, xi)| {
+            let xi: geo_types::MultiLineString = xi.into();
             let xi_len = xi.length::<Euclidean>();
             source_lens.push(xi_len);
             let components = xi
-                .lines()
+                .lines_iter()
                 .map(|li| {
                     let slope = li.slope();
                     let env = CachedEnvelope::new(li);
@@ -245,17 +531,19 @@ fn create_source_rtree(
 }
 
 fn create_target_rtree(
-    y: impl Iterator<Item = geo_types::LineString>,
+    y: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
     target_lens: &mut Vec<f64>,
-    dist: f64,
+    padding: impl Fn(usize) -> f64,
 ) -> TargetTree {
     let to_insert = y
         .enumerate()
         .flat_map(|(i, yi)| {
+            let yi: geo_types::MultiLineString = yi.into();
             let yi_len = yi.length::<Euclidean>();
             target_lens.push(yi_len);
+            let dist = padding(i);
             let components = yi
-                .lines()
+                .lines_iter()
                 .map(|li| {
                     let tl = TarLine(li, dist);
                     let slope = li.slope();
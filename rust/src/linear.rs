@@ -0,0 +1,95 @@
+use geo::{Distance, Euclidean};
+use geo_types::{Coord, Line, LineString, Point};
+
+/// Cumulative length at each vertex of a `LineString`
+fn cumulative_lengths(line: &LineString) -> Vec<f64> {
+    let mut acc = 0.0;
+    let mut res = Vec::with_capacity(line.0.len());
+    res.push(0.0);
+    for l in line.lines() {
+        acc += Euclidean::distance(l.start, l.end);
+        res.push(acc);
+    }
+    res
+}
+
+/// Coordinate at a distance along a `LineString`
+fn coord_at(line: &LineString, cum: &[f64], dist: f64) -> Coord {
+    let k = cum.partition_point(|c| *c < dist).clamp(1, cum.len() - 1);
+    let (d0, d1) = (cum[k - 1], cum[k]);
+    let (c0, c1) = (line.0[k - 1], line.0[k]);
+    if d1 <= d0 {
+        return c0;
+    }
+    let f = (dist - d0) / (d1 - d0);
+    c0 + (c1 - c0) * f
+}
+
+/// Portion of a `LineString` between two fractions of its length
+///
+/// Fractions are clamped to `[0, 1]`. When `start > end` the coordinates
+/// are returned in reverse order.
+pub(crate) fn line_substring(line: &LineString, start: f64, end: f64) -> Vec<Coord> {
+    if line.0.len() < 2 {
+        return line.0.clone();
+    }
+    let (start, end) = (start.clamp(0.0, 1.0), end.clamp(0.0, 1.0));
+    let (lo, hi, reverse) = if start <= end {
+        (start, end, false)
+    } else {
+        (end, start, true)
+    };
+
+    let cum = cumulative_lengths(line);
+    let total = *cum.last().unwrap();
+    let (d_lo, d_hi) = (lo * total, hi * total);
+
+    let mut res = vec![coord_at(line, &cum, d_lo)];
+    res.extend(
+        line.0
+            .iter()
+            .zip(cum.iter())
+            .filter(|(_, d)| **d > d_lo && **d < d_hi)
+            .map(|(c, _)| *c),
+    );
+    res.push(coord_at(line, &cum, d_hi));
+
+    if reverse {
+        res.reverse();
+    }
+    res
+}
+
+/// Fraction of the length of `line` at which `line` is closest to `p`
+pub(crate) fn locate_point(line: &LineString, p: Coord) -> f64 {
+    let cum = cumulative_lengths(line);
+    let total = *cum.last().unwrap();
+    if total <= 0.0 {
+        return 0.0;
+    }
+
+    let p = Point::from(p);
+    let (dist_along, _) = line
+        .lines()
+        .zip(cum.iter())
+        .map(|(l, d0)| {
+            let t = project(&l, p.0);
+            let c = l.start + l.delta() * t;
+            let d = Euclidean::distance(p, Point::from(c));
+            (d0 + t * Euclidean::distance(l.start, l.end), d)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0.0, 0.0));
+
+    dist_along / total
+}
+
+/// Parameter of the orthogonal projection of `p` onto `l` clamped to `[0, 1]`
+pub(crate) fn project(l: &Line, p: Coord) -> f64 {
+    let d = l.delta();
+    let len2 = d.x * d.x + d.y * d.y;
+    if len2 <= 0.0 {
+        return 0.0;
+    }
+    (((p.x - l.start.x) * d.x + (p.y - l.start.y) * d.y) / len2).clamp(0.0, 1.0)
+}
@@ -0,0 +1,146 @@
+use crate::{
+    builder::MatchOptions,
+    interpolate::{
+        interpolate, interpolate_extensive, interpolate_intensive, InterpolatedValue, Tensive,
+    },
+    table::{match_table, MatchRow},
+    tolerance::FeatureTolerances,
+    Anime, AnimeError, MatchesMap,
+};
+
+/// The configuration an [`Anime`] was matched with
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchConfig {
+    pub distance_tolerance: f64,
+    pub angle_tolerance: f64,
+    pub feature_tolerances: FeatureTolerances,
+    pub options: MatchOptions,
+}
+
+/// The result of a match without the geometries
+///
+/// A `MatchModel` holds what interpolation needs, the source and target
+/// lengths and the matches, along with the configuration they were found
+/// with. It is `Send + Sync` and can be cached and shared between threads.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchModel {
+    pub config: MatchConfig,
+    pub source_lens: Vec<f64>,
+    pub target_lens: Vec<f64>,
+    pub matches: MatchesMap,
+}
+
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<MatchModel>();
+};
+
+impl TryFrom<Anime> for MatchModel {
+    type Error = AnimeError;
+
+    fn try_from(anime: Anime) -> Result<Self, Self::Error> {
+        let config = anime.config();
+        let matches = anime
+            .matches
+            .into_inner()
+            .ok_or(AnimeError::MatchesNotFound)?;
+        Ok(MatchModel {
+            config,
+            source_lens: anime.source_lens,
+            target_lens: anime.target_lens,
+            matches,
+        })
+    }
+}
+
+impl Anime {
+    /// The configuration the matches were found with
+    pub fn config(&self) -> MatchConfig {
+        MatchConfig {
+            distance_tolerance: self.distance_tolerance,
+            angle_tolerance: self.angle_tolerance,
+            feature_tolerances: self.feature_tolerances.clone(),
+            options: self.options,
+        }
+    }
+
+    /// Drop the R* Trees and keep what is needed to interpolate
+    pub fn into_model(self) -> Result<MatchModel, AnimeError> {
+        self.try_into()
+    }
+
+    /// Copy what is needed to interpolate, leaving `self` intact
+    pub fn to_model(&self) -> Result<MatchModel, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        Ok(MatchModel {
+            config: self.config(),
+            source_lens: self.source_lens.clone(),
+            target_lens: self.target_lens.clone(),
+            matches: matches.clone(),
+        })
+    }
+}
+
+impl MatchModel {
+    /// Perform numeric attribute interpolation. See [`Anime::interpolate()`].
+    pub fn interpolate(
+        &self,
+        var: &[f64],
+        tensive: Tensive,
+    ) -> Result<Vec<InterpolatedValue>, AnimeError> {
+        interpolate(
+            &self.matches,
+            &self.source_lens,
+            &self.target_lens,
+            var,
+            tensive,
+            false,
+        )
+    }
+
+    /// Interpolation weighting each match by its confidence. See
+    /// [`Anime::interpolate_with_confidence()`].
+    pub fn interpolate_with_confidence(
+        &self,
+        var: &[f64],
+        tensive: Tensive,
+    ) -> Result<Vec<InterpolatedValue>, AnimeError> {
+        interpolate(
+            &self.matches,
+            &self.source_lens,
+            &self.target_lens,
+            var,
+            tensive,
+            true,
+        )
+    }
+
+    /// Extensive interpolation. See [`Anime::interpolate_extensive()`].
+    pub fn interpolate_extensive(
+        &self,
+        source_var: &[f64],
+    ) -> Result<Vec<InterpolatedValue>, AnimeError> {
+        interpolate_extensive(&self.matches, &self.source_lens, source_var, false)
+    }
+
+    /// Intensive interpolation. See [`Anime::interpolate_intensive()`].
+    pub fn interpolate_intensive(
+        &self,
+        source_var: &[f64],
+    ) -> Result<Vec<InterpolatedValue>, AnimeError> {
+        interpolate_intensive(
+            &self.matches,
+            &self.source_lens,
+            &self.target_lens,
+            source_var,
+            false,
+        )
+    }
+
+    /// The matches as a flat table. See [`match_table()`].
+    pub fn match_table(&self) -> Vec<MatchRow> {
+        match_table(&self.matches, &self.source_lens, &self.target_lens)
+    }
+}
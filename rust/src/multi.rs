@@ -0,0 +1,232 @@
+use crate::{
+    cardinality::UnionFind, create_source_rtree, create_target_rtree, find_candidate_matches,
+    tolerance::FeatureTolerances, MatchesMap,
+};
+use geo::{Euclidean, Length};
+use std::collections::BTreeMap;
+
+/// Identifies a single feature within one of several datasets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeatureRef {
+    /// The index of the dataset
+    pub dataset: usize,
+    /// The index of the feature within its dataset
+    pub feature: usize,
+}
+
+/// A consensus road entity grouping matched features across datasets
+#[derive(Debug, Clone)]
+pub struct RoadEntity {
+    /// The member features sorted by dataset then feature index
+    pub members: Vec<FeatureRef>,
+    /// Matched length of the members of each dataset. Indexed by dataset.
+    ///
+    /// For each member this is its largest total shared length with any
+    /// single other dataset, capped at the member's length. It does not grow
+    /// with the number of datasets a member is matched in.
+    pub shared_lens: Vec<f64>,
+}
+
+/// Approximate network matching across more than two networks
+///
+/// Matches are found between each pair of networks `(a, b)` where `a < b`,
+/// using network `a` as the source and network `b` as the target. Every
+/// network but the last is indexed as a source and every network but the
+/// first as a target.
+#[derive(Debug, Clone)]
+pub struct MultiAnime {
+    pub distance_tolerance: f64,
+    pub angle_tolerance: f64,
+    /// Feature lengths, indexed by dataset
+    pub lens: Vec<Vec<f64>>,
+    /// Matches between each pair of datasets keyed by `(source, target)`
+    pub pairwise: BTreeMap<(usize, usize), MatchesMap>,
+}
+
+impl MultiAnime {
+    /// Index `networks` and find matches between every pair of them
+    pub fn new(
+        networks: Vec<Vec<geo_types::LineString>>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+    ) -> Self {
+        let n = networks.len();
+        let mut lens = vec![Vec::new(); n];
+        let mut source_trees = Vec::with_capacity(n);
+        let mut target_trees = Vec::with_capacity(n);
+
+        for (k, lines) in networks.into_iter().enumerate() {
+            let source_tree = (k + 1 < n).then(|| {
+                let mut source_lens = Vec::new();
+                let tree = create_source_rtree(lines.iter().cloned(), &mut source_lens);
+                lens[k] = source_lens;
+                tree
+            });
+            let target_tree = (k > 0).then(|| {
+                let mut target_lens = Vec::new();
+                let tree = create_target_rtree(lines.iter().cloned(), &mut target_lens, |_| {
+                    distance_tolerance
+                });
+                lens[k] = target_lens;
+                tree
+            });
+            if n == 1 {
+                lens[k] = lines.iter().map(|l| l.length::<Euclidean>()).collect();
+            }
+            source_trees.push(source_tree);
+            target_trees.push(target_tree);
+        }
+
+        let feature_tolerances = FeatureTolerances::default();
+        let mut pairwise = BTreeMap::new();
+        for (a, source_tree) in source_trees.iter().enumerate() {
+            let Some(source_tree) = source_tree else {
+                continue;
+            };
+            for (b, target_tree) in target_trees.iter().enumerate().skip(a + 1) {
+                let Some(target_tree) = target_tree else {
+                    continue;
+                };
+                let matches = find_candidate_matches(
+                    source_tree,
+                    target_tree,
+                    angle_tolerance,
+                    distance_tolerance,
+                    &feature_tolerances,
+                    None,
+                );
+                pairwise.insert((a, b), matches);
+            }
+        }
+
+        Self {
+            distance_tolerance,
+            angle_tolerance,
+            lens,
+            pairwise,
+        }
+    }
+
+    /// Number of datasets
+    pub fn n_datasets(&self) -> usize {
+        self.lens.len()
+    }
+
+    /// Matches between two datasets
+    ///
+    /// The lower dataset index is always the source.
+    pub fn matches(&self, a: usize, b: usize) -> Option<&MatchesMap> {
+        self.pairwise.get(&(a.min(b), a.max(b)))
+    }
+
+    /// Group features from all datasets into consensus road entities
+    ///
+    /// Entities are the connected components of the graph formed by all
+    /// pairwise matches with a positive shared length, so features that only
+    /// touch end to end are not joined. Features without any such match form
+    /// an entity of their own, so every feature belongs to exactly one entity.
+    pub fn entities(&self) -> Vec<RoadEntity> {
+        let offsets = self
+            .lens
+            .iter()
+            .scan(0, |acc, l| {
+                let start = *acc;
+                *acc += l.len();
+                Some(start)
+            })
+            .collect::<Vec<_>>();
+        let n_nodes = self.lens.iter().map(Vec::len).sum();
+        let n_datasets = self.n_datasets();
+
+        let mut uf = UnionFind::new(n_nodes);
+        for ((a, b), matches) in self.pairwise.iter() {
+            for (j, cands) in matches.iter() {
+                for ci in cands.iter().filter(|ci| ci.shared_len > 0.0) {
+                    uf.union(offsets[*a] + ci.source_index, offsets[*b] + j);
+                }
+            }
+        }
+
+        let mut entities: BTreeMap<usize, RoadEntity> = BTreeMap::new();
+        for (dataset, l) in self.lens.iter().enumerate() {
+            for feature in 0..l.len() {
+                let root = uf.find(offsets[dataset] + feature);
+                entities
+                    .entry(root)
+                    .or_insert_with(|| RoadEntity {
+                        members: Vec::new(),
+                        shared_lens: vec![0.0; n_datasets],
+                    })
+                    .members
+                    .push(FeatureRef { dataset, feature });
+            }
+        }
+
+        // total shared length of each feature with each other dataset
+        let mut per_dataset: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+        for ((a, b), matches) in self.pairwise.iter() {
+            for (j, cands) in matches.iter() {
+                for ci in cands {
+                    *per_dataset
+                        .entry((offsets[*a] + ci.source_index, *b))
+                        .or_default() += ci.shared_len;
+                    *per_dataset.entry((offsets[*b] + j, *a)).or_default() += ci.shared_len;
+                }
+            }
+        }
+
+        let mut covered = vec![0.0_f64; n_nodes];
+        for ((node, _), shared_len) in per_dataset {
+            covered[node] = covered[node].max(shared_len);
+        }
+        for (dataset, l) in self.lens.iter().enumerate() {
+            for (feature, len) in l.iter().enumerate() {
+                let node = offsets[dataset] + feature;
+                let entity = entities.get_mut(&uf.find(node)).unwrap();
+                entity.shared_lens[dataset] += covered[node].min(*len);
+            }
+        }
+
+        // members are pushed in (dataset, feature) order so they're already sorted
+        let mut res = entities.into_values().collect::<Vec<_>>();
+        res.sort_unstable_by_key(|e| e.members[0]);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain;
+
+    #[test]
+    fn entities_follow_matches_not_connectivity() {
+        let mut third = chain(3, 0.5);
+        // unmatched in every other dataset
+        third.extend(chain(1, 50.0));
+        let multi = MultiAnime::new(vec![chain(3, 0.0), chain(3, 0.0), third], 2.0, 5.0);
+        let entities = multi.entities();
+
+        assert_eq!(entities.len(), 4);
+        for (k, entity) in entities.iter().take(3).enumerate() {
+            let members = (0..3)
+                .map(|dataset| FeatureRef {
+                    dataset,
+                    feature: k,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(entity.members, members);
+            // matched in two other datasets, but counted once
+            assert_eq!(entity.shared_lens, vec![10.0; 3]);
+        }
+        let lone = &entities[3];
+        assert_eq!(
+            lone.members,
+            vec![FeatureRef {
+                dataset: 2,
+                feature: 3
+            }]
+        );
+        assert_eq!(lone.shared_lens, vec![0.0; 3]);
+    }
+}
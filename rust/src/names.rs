@@ -0,0 +1,220 @@
+use crate::constraint::MatchConstraint;
+use std::collections::BTreeSet;
+
+/// String similarity metric used to compare road names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameMetric {
+    /// Jaro-Winkler similarity of the normalised names
+    JaroWinkler,
+    /// Token set ratio of the normalised names
+    ///
+    /// Insensitive to token order and to tokens that are present in only one
+    /// of the names, e.g. `"main street"` and `"north main street"`.
+    TokenSetRatio,
+}
+
+/// Expand a single lower case token of a street name
+fn expand_token(token: &str) -> &str {
+    match token {
+        "st" | "str" => "street",
+        "ave" | "av" => "avenue",
+        "rd" => "road",
+        "dr" => "drive",
+        "blvd" => "boulevard",
+        "ln" => "lane",
+        "ct" => "court",
+        "pl" => "place",
+        "sq" => "square",
+        "ter" => "terrace",
+        "cir" => "circle",
+        "cres" => "crescent",
+        "trl" => "trail",
+        "hwy" => "highway",
+        "fwy" => "freeway",
+        "expy" => "expressway",
+        "pkwy" => "parkway",
+        "rte" => "route",
+        "mt" => "mount",
+        "ft" => "fort",
+        "n" => "north",
+        "s" => "south",
+        "e" => "east",
+        "w" => "west",
+        "ne" => "northeast",
+        "nw" => "northwest",
+        "se" => "southeast",
+        "sw" => "southwest",
+        _ => token,
+    }
+}
+
+/// Normalise a street name for comparison
+///
+/// The name is lower cased, punctuation is removed, and common abbreviations
+/// are expanded, e.g. `"N Main St."` becomes `"north main street"`. A leading
+/// `"St"` followed by other tokens is expanded to `"saint"`.
+pub fn normalize_name(name: &str) -> String {
+    let cleaned = name
+        .chars()
+        .filter(|c| *c != '\'' && *c != '.')
+        .flat_map(|c| {
+            let c = if c.is_alphanumeric() { c } else { ' ' };
+            c.to_lowercase()
+        })
+        .collect::<String>();
+
+    let tokens = cleaned.split_whitespace().collect::<Vec<_>>();
+    tokens
+        .iter()
+        .enumerate()
+        .map(|(k, t)| {
+            if k == 0 && *t == "st" && tokens.len() > 1 {
+                "saint"
+            } else {
+                expand_token(t)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Token set ratio between two normalised names
+///
+/// The tokens of each name are split into their intersection and the
+/// remaining tokens of each name. The result is the highest normalised
+/// Levenshtein similarity between the sorted intersection and each name.
+pub fn token_set_ratio(a: &str, b: &str) -> f64 {
+    let ta = a.split_whitespace().collect::<BTreeSet<_>>();
+    let tb = b.split_whitespace().collect::<BTreeSet<_>>();
+
+    let join = |it: &mut dyn Iterator<Item = &&str>| it.copied().collect::<Vec<_>>().join(" ");
+    let common = join(&mut ta.intersection(&tb));
+    let diff_ab = join(&mut ta.difference(&tb));
+    let diff_ba = join(&mut tb.difference(&ta));
+
+    let combine = |x: &str, y: &str| format!("{x} {y}").trim().to_string();
+    let t1 = combine(&common, &diff_ab);
+    let t2 = combine(&common, &diff_ba);
+
+    let ratio = |x: &str, y: &str| {
+        if x.is_empty() && y.is_empty() {
+            // two empty strings carry no information
+            0.0
+        } else {
+            strsim::normalized_levenshtein(x, y)
+        }
+    };
+
+    ratio(&common, &t1)
+        .max(ratio(&common, &t2))
+        .max(ratio(&t1, &t2))
+}
+
+/// Similarity of two street names in the range `[0, 1]`
+///
+/// Both names are normalised with [`normalize_name()`] before comparison.
+pub fn name_similarity(a: &str, b: &str, metric: NameMetric) -> f64 {
+    let (a, b) = (normalize_name(a), normalize_name(b));
+    similarity_normalized(&a, &b, metric)
+}
+
+fn similarity_normalized(a: &str, b: &str, metric: NameMetric) -> f64 {
+    match metric {
+        NameMetric::JaroWinkler => strsim::jaro_winkler(a, b),
+        NameMetric::TokenSetRatio => token_set_ratio(a, b),
+    }
+}
+
+/// Street name similarity as a [`MatchConstraint`]
+///
+/// The weight of a pair is the similarity of the source and target names.
+/// Pairs below `min_similarity` are excluded. When either name is missing
+/// or empty the pair is neither excluded nor down-weighted.
+///
+/// Combine it with other constraints using a tuple, e.g. `(matrix, names)`.
+#[derive(Debug, Clone)]
+pub struct NameSimilarity {
+    source_names: Vec<Option<String>>,
+    target_names: Vec<Option<String>>,
+    metric: NameMetric,
+    min_similarity: f64,
+}
+
+impl NameSimilarity {
+    /// Create a new name constraint from source and target names
+    pub fn new<S: AsRef<str>>(
+        source_names: impl IntoIterator<Item = Option<S>>,
+        target_names: impl IntoIterator<Item = Option<S>>,
+        metric: NameMetric,
+        min_similarity: f64,
+    ) -> Self {
+        let normalize_all = |names: Vec<Option<S>>| {
+            names
+                .into_iter()
+                .map(|n| {
+                    n.map(|n| normalize_name(n.as_ref()))
+                        .filter(|n| !n.is_empty())
+                })
+                .collect::<Vec<_>>()
+        };
+        Self {
+            source_names: normalize_all(source_names.into_iter().collect()),
+            target_names: normalize_all(target_names.into_iter().collect()),
+            metric,
+            min_similarity,
+        }
+    }
+}
+
+impl MatchConstraint for NameSimilarity {
+    fn weight(&self, source_index: usize, target_index: usize) -> f64 {
+        let source = self.source_names.get(source_index).and_then(Option::as_ref);
+        let target = self.target_names.get(target_index).and_then(Option::as_ref);
+
+        match (source, target) {
+            (Some(s), Some(t)) => {
+                let sim = similarity_normalized(s, t, self.metric);
+                if sim >= self.min_similarity {
+                    sim
+                } else {
+                    0.0
+                }
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_normalized() {
+        assert_eq!(normalize_name("N Main St."), "north main street");
+        assert_eq!(normalize_name("St Mary St"), "saint mary street");
+        assert_eq!(normalize_name("St."), "street");
+        assert_eq!(normalize_name("RUE DE L'ÉGLISE"), "rue de léglise");
+    }
+
+    #[test]
+    fn token_set_ratio_ignores_extra_tokens() {
+        assert_eq!(token_set_ratio("main street", "north main street"), 1.0);
+        assert_eq!(token_set_ratio("", ""), 0.0);
+        assert!(token_set_ratio("main street", "oak avenue") < 0.5);
+    }
+
+    #[test]
+    fn missing_names_do_not_constrain_matches() {
+        let names = NameSimilarity::new(
+            [Some("Main St"), None, Some("")],
+            [Some("main street"), Some("Oak Ave")],
+            NameMetric::TokenSetRatio,
+            0.8,
+        );
+        assert_eq!(names.weight(0, 0), 1.0);
+        assert_eq!(names.weight(0, 1), 0.0);
+        assert_eq!(names.weight(1, 1), 1.0);
+        assert_eq!(names.weight(2, 1), 1.0);
+    }
+}
@@ -1,4 +1,4 @@
-use geo_types::{Line, Point, Rect};
+use geo_types::{Coord, Line, Point, Rect};
 use std::ops::Range;
 
 // TODO for handling geographic CRS
@@ -54,3 +54,21 @@ pub(crate) fn solve_no_x_overlap(y_overlap: Range<f64>, x: &Line, slope: &f64) -
     let p2 = Point::new(x2, y_overlap.end);
     (p1, p2)
 }
+
+/// Returns `true` if the two lines point the same way
+pub(crate) fn same_direction(x: &Line, y: &Line) -> bool {
+    let (dx, dy) = (x.delta(), y.delta());
+    dx.x * dy.x + dx.y * dy.y > 0.0
+}
+
+// Project the end points of y onto x and measure the length of x between them
+pub(crate) fn projected_overlap(x: &Line, y: &Line) -> f64 {
+    let d = x.delta();
+    let len_sq = d.x * d.x + d.y * d.y;
+    if len_sq == 0.0 {
+        return 0.0;
+    }
+    let t =
+        |p: Coord| (((p.x - x.start.x) * d.x + (p.y - x.start.y) * d.y) / len_sq).clamp(0.0, 1.0);
+    (t(y.end) - t(y.start)).abs() * len_sq.sqrt()
+}
@@ -0,0 +1,78 @@
+//! Saving and loading finished matches
+//!
+//! A match is saved as a [`MatchModel`]. The R* Trees are not persisted, so
+//! a loaded model can interpolate but not find new matches.
+use crate::{io::io_err, model::MatchModel, Anime, AnimeError};
+use std::io::{Read, Write};
+
+impl Anime {
+    /// Write the matches and configuration as JSON. See [`MatchModel::read_json()`].
+    pub fn write_json(&self, writer: impl Write) -> Result<(), AnimeError> {
+        self.to_model()?.write_json(writer)
+    }
+
+    /// Write the matches and configuration in the compact MessagePack format.
+    /// See [`MatchModel::read_binary()`].
+    pub fn write_binary(&self, writer: impl Write) -> Result<(), AnimeError> {
+        self.to_model()?.write_binary(writer)
+    }
+}
+
+impl MatchModel {
+    /// Write the matches and configuration as JSON
+    pub fn write_json(&self, writer: impl Write) -> Result<(), AnimeError> {
+        serde_json::to_writer(writer, self).map_err(io_err)
+    }
+
+    /// Read matches and configuration written with [`MatchModel::write_json()`]
+    /// or [`Anime::write_json()`]
+    pub fn read_json(reader: impl Read) -> Result<Self, AnimeError> {
+        serde_json::from_reader(reader).map_err(io_err)
+    }
+
+    /// Write the matches and configuration in the compact MessagePack format
+    pub fn write_binary(&self, mut writer: impl Write) -> Result<(), AnimeError> {
+        rmp_serde::encode::write(&mut writer, self).map_err(io_err)
+    }
+
+    /// Read matches and configuration written with [`MatchModel::write_binary()`]
+    /// or [`Anime::write_binary()`]
+    pub fn read_binary(reader: impl Read) -> Result<Self, AnimeError> {
+        rmp_serde::decode::from_read(reader).map_err(io_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::{AnimeBuilder, DirectionMode};
+    use crate::model::MatchModel;
+    use geo_types::line_string;
+
+    #[test]
+    fn saved_model_keeps_configuration_and_matches() {
+        let source = vec![line_string![(x: 0.0, y: 0.0), (x: 10.0, y: 0.0)]];
+        let target = vec![line_string![(x: 0.0, y: 1.0), (x: 10.0, y: 1.0)]];
+        let anime = AnimeBuilder::new(source.into_iter(), target.into_iter())
+            .distance_tolerance(2.0)
+            .direction_mode(DirectionMode::Directed)
+            .find_matches()
+            .unwrap()
+            .into_anime();
+
+        let mut json = Vec::new();
+        anime.write_json(&mut json).unwrap();
+        let mut binary = Vec::new();
+        anime.write_binary(&mut binary).unwrap();
+
+        for model in [
+            MatchModel::read_json(json.as_slice()).unwrap(),
+            MatchModel::read_binary(binary.as_slice()).unwrap(),
+        ] {
+            assert_eq!(model.config.distance_tolerance, 2.0);
+            assert_eq!(model.config.options, anime.options);
+            assert_eq!(model.source_lens, anime.source_lens);
+            assert_eq!(model.match_table().len(), 1);
+            assert_eq!(model.match_table()[0].shared_len, 10.0);
+        }
+    }
+}
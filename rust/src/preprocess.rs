@@ -0,0 +1,128 @@
+use crate::{
+    builder::MatchOptions, create_source_rtree, create_target_rtree, tolerance::FeatureTolerances,
+    Anime,
+};
+use geo::{line_measures::Densify, Euclidean, Length, Simplify, SimplifyVw};
+use geo_types::{LineString, MultiLineString};
+
+/// Preprocessing applied to geometries before they are indexed
+///
+/// Densifying long segments keeps the padded target envelopes small which
+/// reduces the number of false candidates. Simplifying over-digitised lines
+/// reduces the number of component lines in each tree.
+///
+/// Used by [`Anime::load_geometries_preprocessed()`] or set with
+/// [`crate::builder::AnimeBuilder::preprocess()`]. Tolerances are in
+/// the units of the projected coordinates, so meters for [`crate::builder::CrsMode::Geographic`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Preprocess {
+    /// Index the geometries as they are
+    #[default]
+    None,
+    /// Split segments so that none is longer than the maximum segment length
+    Densify(f64),
+    /// Ramer–Douglas–Peucker simplification with a distance tolerance
+    DouglasPeucker(f64),
+    /// Visvalingam-Whyatt simplification with an area tolerance
+    Visvalingam(f64),
+}
+
+impl Preprocess {
+    /// Apply the preprocessing step to a single `LineString`
+    pub fn apply(&self, line: LineString) -> LineString {
+        match self {
+            Preprocess::None => line,
+            Preprocess::Densify(max_len) if *max_len > 0.0 => line.densify::<Euclidean>(*max_len),
+            // a non-positive maximum segment length leaves the line as is
+            Preprocess::Densify(_) => line,
+            Preprocess::DouglasPeucker(eps) => line.simplify(eps),
+            Preprocess::Visvalingam(eps) => line.simplify_vw(eps),
+        }
+    }
+
+    /// Apply the preprocessing step to each part of a `MultiLineString`
+    pub fn apply_lines(&self, lines: MultiLineString) -> MultiLineString {
+        lines.into_iter().map(|l| self.apply(l)).collect()
+    }
+}
+
+impl Anime {
+    /// Load source and target geometries with preprocessing
+    ///
+    /// Like [`Anime::load_geometries()`] but each geometry is densified or
+    /// simplified before it is indexed. `source_lens` and `target_lens`
+    /// are the lengths of the original geometries so that shared lengths are
+    /// reported against the original feature lengths.
+    pub fn load_geometries_preprocessed(
+        source: impl Iterator<Item = impl Into<MultiLineString>>,
+        target: impl Iterator<Item = impl Into<MultiLineString>>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+        preprocess: Preprocess,
+    ) -> Self {
+        let mut source_lens = Vec::new();
+        let mut target_lens = Vec::new();
+
+        // the trees record the lengths of the processed geometries, which are discarded
+        let mut processed_lens = Vec::new();
+        let source = source.map(|s| {
+            let s = s.into();
+            source_lens.push(s.length::<Euclidean>());
+            preprocess.apply_lines(s)
+        });
+        let source_tree = create_source_rtree(source, &mut processed_lens);
+
+        processed_lens.clear();
+        let target = target.map(|t| {
+            let t = t.into();
+            target_lens.push(t.length::<Euclidean>());
+            preprocess.apply_lines(t)
+        });
+        let target_tree = create_target_rtree(target, &mut processed_lens, |_| distance_tolerance);
+
+        let options = MatchOptions {
+            preprocess,
+            ..Default::default()
+        };
+        Self::from_trees(
+            distance_tolerance,
+            angle_tolerance,
+            FeatureTolerances::default(),
+            options,
+            (source_tree, source_lens),
+            (target_tree, target_lens),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+
+    #[test]
+    fn preprocessing_keeps_original_lengths() {
+        let source = [line_string![(x: 0.0, y: 0.0), (x: 100.0, y: 0.0)]];
+        let target = [line_string![
+            (x: 0.0, y: 1.0),
+            (x: 50.0, y: 1.0),
+            (x: 100.0, y: 1.0),
+        ]];
+        let mut anime = Anime::load_geometries_preprocessed(
+            source.into_iter(),
+            target.into_iter(),
+            5.0,
+            5.0,
+            Preprocess::Densify(10.0),
+        );
+        assert_eq!(anime.source_tree.size(), 10);
+        assert_eq!(anime.target_tree.size(), 10);
+        assert_eq!(anime.source_lens, vec![100.0]);
+        assert_eq!(anime.options.preprocess, Preprocess::Densify(10.0));
+
+        anime.find_matches().unwrap();
+        let matches = anime.matches.get().unwrap();
+        assert!((matches[&0][0].shared_len - 100.0).abs() < 1e-9);
+    }
+}
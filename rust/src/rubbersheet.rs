@@ -0,0 +1,376 @@
+use crate::{Anime, AnimeError};
+use geo::{BoundingRect, Closest, ClosestPoint, MapCoords};
+use geo_types::{Coord, MultiLineString, Point, Rect};
+use rstar::{primitives::GeomWithData, RTree};
+use std::collections::BTreeMap;
+
+/// A pair of corresponding locations in the source and target networks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControlPoint {
+    pub source: Coord,
+    pub target: Coord,
+}
+
+/// Options for piecewise rubber-sheeting
+#[derive(Debug, Clone)]
+pub struct RubberSheetOptions {
+    /// Width and height of each tile of the warp grid
+    pub tile_size: f64,
+    /// Minimum coverage of both features for a matched pair to provide control points
+    pub min_coverage: f64,
+    /// Number of nearest control points used to interpolate each grid node
+    pub neighbours: usize,
+    /// Largest number of grid nodes that may be allocated for the warp
+    pub max_grid_nodes: usize,
+}
+
+impl Default for RubberSheetOptions {
+    fn default() -> Self {
+        Self {
+            tile_size: 1000.0,
+            min_coverage: 0.9,
+            neighbours: 8,
+            max_grid_nodes: 1 << 22,
+        }
+    }
+}
+
+/// A local warp of the source geometries onto the target geometries
+///
+/// The extent of the source geometries is divided into square tiles. The
+/// displacement at each tile corner is the inverse distance weighted mean
+/// displacement of the nearest control points. Locations are warped by
+/// bilinear interpolation of the displacements at the corners of their tile.
+/// Locations outside of the grid use the displacement of the nearest edge.
+#[derive(Debug, Clone)]
+pub struct RubberSheet {
+    control_points: Vec<ControlPoint>,
+    origin: Coord,
+    tile_size: f64,
+    nx: usize,
+    ny: usize,
+    // displacement at each grid node, row major with `nx + 1` columns
+    displacements: Vec<Coord>,
+}
+
+impl RubberSheet {
+    /// Create a warp over `extent` from a set of control points
+    ///
+    /// Returns [`AnimeError::InvalidOption`] unless `tile_size` is finite and
+    /// positive, the grid over `extent` has at most `max_grid_nodes` nodes and
+    /// `neighbours` is at least 1.
+    pub fn from_control_points(
+        control_points: Vec<ControlPoint>,
+        extent: Rect,
+        options: &RubberSheetOptions,
+    ) -> Result<Self, AnimeError> {
+        let tile_size = options.tile_size;
+        if !(tile_size.is_finite() && tile_size > 0.0) {
+            return Err(AnimeError::InvalidOption(format!(
+                "`tile_size` must be finite and greater than 0, got {tile_size}"
+            )));
+        }
+        if options.neighbours == 0 {
+            return Err(AnimeError::InvalidOption(
+                "`neighbours` must be at least 1".to_string(),
+            ));
+        }
+        let nx = (extent.width() / tile_size).ceil().max(1.0);
+        let ny = (extent.height() / tile_size).ceil().max(1.0);
+        let n_nodes = (nx + 1.0) * (ny + 1.0);
+        if n_nodes > options.max_grid_nodes as f64 {
+            return Err(AnimeError::InvalidOption(format!(
+                "`tile_size` {tile_size} needs {n_nodes} grid nodes, more than `max_grid_nodes` {}",
+                options.max_grid_nodes
+            )));
+        }
+        let (nx, ny) = (nx as usize, ny as usize);
+
+        if control_points.is_empty() {
+            return Err(AnimeError::InsufficientCorrespondences);
+        }
+
+        let tree = RTree::bulk_load(
+            control_points
+                .iter()
+                .map(|cp| GeomWithData::new(Point::from(cp.source), cp.target - cp.source))
+                .collect(),
+        );
+
+        let origin = extent.min();
+        let k = options.neighbours;
+
+        let mut displacements = Vec::with_capacity((nx + 1) * (ny + 1));
+        for iy in 0..=ny {
+            for ix in 0..=nx {
+                let node = Point::new(
+                    origin.x + ix as f64 * tile_size,
+                    origin.y + iy as f64 * tile_size,
+                );
+                displacements.push(idw(&tree, node, k));
+            }
+        }
+
+        Ok(Self {
+            control_points,
+            origin,
+            tile_size,
+            nx,
+            ny,
+            displacements,
+        })
+    }
+
+    /// Derive control points from high confidence matches and create a warp
+    ///
+    /// Each source feature is paired with the target it shares the most
+    /// length with. Pairs are used when both features are covered at least
+    /// `min_coverage`, so control points also come from connected networks
+    /// whose matches are not 1:1. Each vertex of the source feature
+    /// within the distance tolerance of the target feature is paired with the
+    /// closest point on the target feature. The grid covers the extent of `source`.
+    /// Matches found with [`crate::builder::CrsMode::Geographic`] can't be used.
+    pub fn from_matches(
+        anime: &Anime,
+        source: &[MultiLineString],
+        target: &[MultiLineString],
+        options: &RubberSheetOptions,
+    ) -> Result<Self, AnimeError> {
+        anime.require_projected("RubberSheet::from_matches")?;
+        let control_points = control_points(anime, source, target, options.min_coverage)?;
+        let extent = source
+            .iter()
+            .filter_map(|s| s.bounding_rect())
+            .reduce(|a, b| {
+                let (amin, amax, bmin, bmax) = (a.min(), a.max(), b.min(), b.max());
+                Rect::new(
+                    Coord {
+                        x: amin.x.min(bmin.x),
+                        y: amin.y.min(bmin.y),
+                    },
+                    Coord {
+                        x: amax.x.max(bmax.x),
+                        y: amax.y.max(bmax.y),
+                    },
+                )
+            })
+            .ok_or(AnimeError::InsufficientCorrespondences)?;
+        Self::from_control_points(control_points, extent, options)
+    }
+
+    /// The control points used to create the warp
+    pub fn control_points(&self) -> &[ControlPoint] {
+        &self.control_points
+    }
+
+    /// Warp a single coordinate
+    pub fn warp_coord(&self, c: Coord) -> Coord {
+        let gx = ((c.x - self.origin.x) / self.tile_size).clamp(0.0, self.nx as f64);
+        let gy = ((c.y - self.origin.y) / self.tile_size).clamp(0.0, self.ny as f64);
+        let ix = (gx.floor() as usize).min(self.nx - 1);
+        let iy = (gy.floor() as usize).min(self.ny - 1);
+        let (fx, fy) = (gx - ix as f64, gy - iy as f64);
+
+        let node = |ix: usize, iy: usize| self.displacements[iy * (self.nx + 1) + ix];
+        let d00 = node(ix, iy);
+        let d10 = node(ix + 1, iy);
+        let d01 = node(ix, iy + 1);
+        let d11 = node(ix + 1, iy + 1);
+
+        let d = d00 * ((1.0 - fx) * (1.0 - fy))
+            + d10 * (fx * (1.0 - fy))
+            + d01 * ((1.0 - fx) * fy)
+            + d11 * (fx * fy);
+        c + d
+    }
+
+    /// Warp every coordinate of a geometry, such as a `LineString` or `MultiLineString`
+    pub fn warp<G>(&self, geom: &G) -> G
+    where
+        G: MapCoords<f64, f64, Output = G>,
+    {
+        geom.map_coords(|c| self.warp_coord(c))
+    }
+}
+
+/// Inverse distance weighted displacement of the `k` nearest control points
+fn idw(tree: &RTree<GeomWithData<Point, Coord>>, at: Point, k: usize) -> Coord {
+    let mut num = Coord { x: 0.0, y: 0.0 };
+    let mut den = 0.0;
+    for cp in tree.nearest_neighbor_iter(&at).take(k) {
+        let d2 = {
+            let p = cp.geom();
+            (p.x() - at.x()).powi(2) + (p.y() - at.y()).powi(2)
+        };
+        if d2 == 0.0 {
+            return cp.data;
+        }
+        let w = 1.0 / d2;
+        num = num + cp.data * w;
+        den += w;
+    }
+    num / den
+}
+
+fn control_points(
+    anime: &Anime,
+    source: &[MultiLineString],
+    target: &[MultiLineString],
+    min_coverage: f64,
+) -> Result<Vec<ControlPoint>, AnimeError> {
+    if source.len() != anime.source_lens.len() || target.len() != anime.target_lens.len() {
+        return Err(AnimeError::IncorrectGeometryLength);
+    }
+    let matches = anime.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+    let coverage = anime.coverage_report(min_coverage)?;
+
+    // the target sharing the most length with each source
+    let mut best: BTreeMap<usize, (usize, f64)> = BTreeMap::new();
+    for (j, cands) in matches.iter() {
+        for ci in cands.iter().filter(|ci| ci.shared_len > 0.0) {
+            let entry = best.entry(ci.source_index).or_insert((*j, ci.shared_len));
+            if ci.shared_len > entry.1 {
+                *entry = (*j, ci.shared_len);
+            }
+        }
+    }
+
+    let res = best
+        .into_iter()
+        .map(|(i, (j, _))| (i, j))
+        .filter(|(i, j)| {
+            coverage.source_coverage[*i] >= min_coverage
+                && coverage.target_coverage[*j] >= min_coverage
+        })
+        .flat_map(|(i, j)| {
+            let tj = &target[j];
+            source[i]
+                .iter()
+                .flat_map(|l| l.coords())
+                .filter_map(move |c| {
+                    let p = Point::from(*c);
+                    match tj.closest_point(&p) {
+                        Closest::Intersection(q) | Closest::SinglePoint(q) => {
+                            let d = (q.x() - p.x()).hypot(q.y() - p.y());
+                            (d <= anime.distance_tolerance).then_some(ControlPoint {
+                                source: *c,
+                                target: q.0,
+                            })
+                        }
+                        Closest::Indeterminate => None,
+                    }
+                })
+        })
+        .collect();
+    Ok(res)
+}
+
+impl Anime {
+    /// Rubber-sheet the source onto the target and match again
+    ///
+    /// A first matching pass is used to derive a [`RubberSheet`] with
+    /// [`RubberSheet::from_matches()`]. The source geometries are warped
+    /// and a second matching pass is run on the warped geometries.
+    /// Returns the second pass along with the warp that was applied.
+    pub fn new_rubber_sheeted(
+        source: impl Iterator<Item = impl Into<MultiLineString>>,
+        target: impl Iterator<Item = impl Into<MultiLineString>>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+        options: &RubberSheetOptions,
+    ) -> Result<(Self, RubberSheet), AnimeError> {
+        let source = source.map(Into::into).collect::<Vec<_>>();
+        let target = target.map(Into::into).collect::<Vec<_>>();
+        let first = Anime::new(
+            source.iter().cloned(),
+            target.iter().cloned(),
+            distance_tolerance,
+            angle_tolerance,
+        );
+        let sheet = RubberSheet::from_matches(&first, &source, &target, options)?;
+        let warped = source.iter().map(|s| sheet.warp(s));
+        let anime = Anime::new(
+            warped,
+            target.into_iter(),
+            distance_tolerance,
+            angle_tolerance,
+        );
+        Ok((anime, sheet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent() -> Rect {
+        Rect::new(Coord { x: 0.0, y: 0.0 }, Coord { x: 100.0, y: 50.0 })
+    }
+
+    #[test]
+    fn zero_displacements_are_an_identity_warp() {
+        let control_points = [(10.0, 10.0), (90.0, 5.0), (50.0, 45.0)]
+            .into_iter()
+            .map(|c| ControlPoint {
+                source: c.into(),
+                target: c.into(),
+            })
+            .collect();
+        let options = RubberSheetOptions {
+            tile_size: 10.0,
+            ..Default::default()
+        };
+        let sheet = RubberSheet::from_control_points(control_points, extent(), &options).unwrap();
+
+        // inside, on the edge of and outside of the grid
+        for c in [(0.0, 0.0), (33.3, 12.5), (100.0, 50.0), (-20.0, 80.0)] {
+            let c = Coord::from(c);
+            assert_eq!(sheet.warp_coord(c), c);
+        }
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let control_points = vec![ControlPoint {
+            source: Coord { x: 0.0, y: 0.0 },
+            target: Coord { x: 1.0, y: 1.0 },
+        }];
+        for (tile_size, neighbours) in [
+            (0.0, 8),
+            (-1.0, 8),
+            (f64::NAN, 8),
+            (1e-300, 8),
+            (1e-4, 8),
+            (10.0, 0),
+        ] {
+            let options = RubberSheetOptions {
+                tile_size,
+                neighbours,
+                ..Default::default()
+            };
+            let res = RubberSheet::from_control_points(control_points.clone(), extent(), &options);
+            assert!(matches!(res, Err(AnimeError::InvalidOption(_))));
+        }
+    }
+
+    #[test]
+    fn connected_networks_provide_control_points() {
+        let source = crate::chain(10, 0.0);
+        let target = source
+            .iter()
+            .map(|l| l.map_coords(|c| c + Coord { x: 0.5, y: 0.5 }))
+            .collect::<Vec<_>>();
+        let options = RubberSheetOptions {
+            tile_size: 10.0,
+            ..Default::default()
+        };
+        let (anime, sheet) =
+            Anime::new_rubber_sheeted(source.into_iter(), target.into_iter(), 2.0, 5.0, &options)
+                .unwrap();
+
+        assert!(!sheet.control_points().is_empty());
+        let warped = sheet.warp_coord(Coord { x: 50.0, y: 0.0 });
+        assert!((warped.y - 0.5).abs() < 1e-9);
+        assert!(anime.matches.get().is_some());
+    }
+}
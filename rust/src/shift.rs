@@ -0,0 +1,334 @@
+use crate::{Anime, AnimeError};
+use geo::{AffineOps, AffineTransform, Closest, ClosestPoint};
+use geo_types::{Coord, Line, LineString, Point};
+use rstar::{primitives::GeomWithData, RTree};
+
+/// Transformation model used to correct a systematic shift
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftModel {
+    /// A constant x and y offset
+    Translation,
+    /// A full affine transformation including rotation, scale, and skew
+    Affine,
+}
+
+/// Options for estimating a systematic shift between networks
+///
+/// The shift is estimated with an iterative closest point (ICP) procedure.
+/// The midpoint of each source component line is paired with the closest
+/// point on the nearest target component line that is within
+/// `search_radius` and `angle_tolerance`. A transformation is then fit to
+/// the pairs, the source midpoints are moved, and the process is repeated.
+#[derive(Debug, Clone)]
+pub struct ShiftOptions {
+    pub model: ShiftModel,
+    /// Maximum distance between a source midpoint and a target line
+    pub search_radius: f64,
+    /// Maximum angle difference, in degrees, between paired lines
+    pub angle_tolerance: f64,
+    /// Maximum number of ICP iterations
+    pub max_iterations: usize,
+    /// ICP stops when no point moves more than this distance between iterations
+    pub convergence: f64,
+}
+
+impl Default for ShiftOptions {
+    fn default() -> Self {
+        Self {
+            model: ShiftModel::Translation,
+            search_radius: 20.0,
+            angle_tolerance: 10.0,
+            max_iterations: 20,
+            convergence: 1e-3,
+        }
+    }
+}
+
+type IndexedLine = GeomWithData<Line, f64>;
+
+/// Undirected angle of a line in degrees in the range `[-90, 90]`
+fn line_angle(l: &Line) -> f64 {
+    l.slope().atan().to_degrees()
+}
+
+/// Smallest difference between two undirected angles
+fn angle_diff(a: f64, b: f64) -> f64 {
+    let d = (a - b).abs() % 180.0;
+    d.min(180.0 - d)
+}
+
+fn median(x: &mut [f64]) -> f64 {
+    x.sort_unstable_by(|a, b| a.total_cmp(b));
+    let n = x.len();
+    if n % 2 == 1 {
+        x[n / 2]
+    } else {
+        (x[n / 2 - 1] + x[n / 2]) / 2.0
+    }
+}
+
+/// Fit a translation as the component-wise median offset
+fn fit_translation(pairs: &[(Coord, Coord)]) -> AffineTransform {
+    let mut dx = pairs.iter().map(|(p, c)| c.x - p.x).collect::<Vec<_>>();
+    let mut dy = pairs.iter().map(|(p, c)| c.y - p.y).collect::<Vec<_>>();
+    AffineTransform::translate(median(&mut dx), median(&mut dy))
+}
+
+/// Least squares affine fit. Returns `None` if the system is singular.
+fn fit_affine_lsq(pairs: &[(Coord, Coord)]) -> Option<AffineTransform> {
+    let n = pairs.len() as f64;
+    let (mut px, mut py, mut cx, mut cy) = (0.0, 0.0, 0.0, 0.0);
+    for (p, c) in pairs {
+        px += p.x;
+        py += p.y;
+        cx += c.x;
+        cy += c.y;
+    }
+    let (px, py, cx, cy) = (px / n, py / n, cx / n, cy / n);
+
+    // with centered coordinates the offset decouples from the linear part
+    let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+    let (mut sx_u, mut sy_u, mut sx_v, mut sy_v) = (0.0, 0.0, 0.0, 0.0);
+    for (p, c) in pairs {
+        let (x, y) = (p.x - px, p.y - py);
+        let (u, v) = (c.x - cx, c.y - cy);
+        sxx += x * x;
+        sxy += x * y;
+        syy += y * y;
+        sx_u += x * u;
+        sy_u += y * u;
+        sx_v += x * v;
+        sy_v += y * v;
+    }
+
+    let det = sxx * syy - sxy * sxy;
+    if det.abs() <= f64::EPSILON * (sxx * syy).abs().max(1.0) {
+        return None;
+    }
+    let a = (sx_u * syy - sy_u * sxy) / det;
+    let b = (sy_u * sxx - sx_u * sxy) / det;
+    let d = (sx_v * syy - sy_v * sxy) / det;
+    let e = (sy_v * sxx - sx_v * sxy) / det;
+    let xoff = cx - a * px - b * py;
+    let yoff = cy - d * px - e * py;
+    Some(AffineTransform::new(a, b, xoff, d, e, yoff))
+}
+
+/// Affine fit with a single round of outlier rejection
+///
+/// Pairs with a residual larger than three times the median residual of
+/// the first fit are dropped before refitting.
+fn fit_affine(pairs: &[(Coord, Coord)]) -> Option<AffineTransform> {
+    let first = fit_affine_lsq(pairs)?;
+    let residuals = pairs
+        .iter()
+        .map(|(p, c)| {
+            let q = first.apply(*p);
+            (q.x - c.x).hypot(q.y - c.y)
+        })
+        .collect::<Vec<_>>();
+    let cutoff = 3.0 * median(&mut residuals.clone());
+    if cutoff <= 0.0 {
+        return Some(first);
+    }
+    let inliers = pairs
+        .iter()
+        .zip(residuals)
+        .filter(|(_, r)| *r <= cutoff)
+        .map(|(pc, _)| *pc)
+        .collect::<Vec<_>>();
+    if inliers.len() < 3 {
+        return Some(first);
+    }
+    fit_affine_lsq(&inliers)
+}
+
+/// Pair each moved source midpoint with the closest point on a nearby target line
+fn correspondences(
+    midpoints: &[(Coord, Line)],
+    transform: &AffineTransform,
+    target_tree: &RTree<IndexedLine>,
+    options: &ShiftOptions,
+) -> Vec<(Coord, Coord)> {
+    let r2 = options.search_radius * options.search_radius;
+    midpoints
+        .iter()
+        .filter_map(|(p, l)| {
+            let moved = l.affine_transform(transform);
+            let q = transform.apply(*p);
+            let angle = line_angle(&moved);
+            target_tree
+                .locate_within_distance([q.x, q.y].into(), r2)
+                .filter(|t| angle_diff(angle, t.data) <= options.angle_tolerance)
+                .filter_map(|t| match t.geom().closest_point(&Point::from(q)) {
+                    Closest::Intersection(c) | Closest::SinglePoint(c) => Some(c.0),
+                    Closest::Indeterminate => None,
+                })
+                .min_by(|a, b| {
+                    let da = (a.x - q.x).hypot(a.y - q.y);
+                    let db = (b.x - q.x).hypot(b.y - q.y);
+                    da.total_cmp(&db)
+                })
+                .map(|c| (*p, c))
+        })
+        .collect()
+}
+
+/// Estimate a systematic shift of `source` relative to `target`
+///
+/// Returns the transformation that moves the source geometries onto the
+/// target geometries. See [`ShiftOptions`] for a description of the procedure.
+pub fn estimate_shift(
+    source: &[LineString],
+    target: &[LineString],
+    options: &ShiftOptions,
+) -> Result<AffineTransform, AnimeError> {
+    let target_tree = RTree::bulk_load(
+        target
+            .iter()
+            .flat_map(|t| t.lines())
+            .map(|l| GeomWithData::new(l, line_angle(&l)))
+            .collect(),
+    );
+    let midpoints = source
+        .iter()
+        .flat_map(|s| s.lines())
+        .map(|l| {
+            let mid = Coord {
+                x: (l.start.x + l.end.x) / 2.0,
+                y: (l.start.y + l.end.y) / 2.0,
+            };
+            (mid, l)
+        })
+        .collect::<Vec<_>>();
+
+    let min_pairs = match options.model {
+        ShiftModel::Translation => 1,
+        ShiftModel::Affine => 3,
+    };
+
+    let mut transform = AffineTransform::identity();
+    for _ in 0..options.max_iterations {
+        let pairs = correspondences(&midpoints, &transform, &target_tree, options);
+        if pairs.len() < min_pairs {
+            return Err(AnimeError::InsufficientCorrespondences);
+        }
+        let next = match options.model {
+            ShiftModel::Translation => fit_translation(&pairs),
+            ShiftModel::Affine => {
+                fit_affine(&pairs).ok_or(AnimeError::InsufficientCorrespondences)?
+            }
+        };
+
+        // largest movement of any paired point between iterations
+        let max_step = pairs
+            .iter()
+            .map(|(p, _)| {
+                let (a, b) = (transform.apply(*p), next.apply(*p));
+                (a.x - b.x).hypot(a.y - b.y)
+            })
+            .fold(0.0, f64::max);
+
+        transform = next;
+        if max_step <= options.convergence {
+            break;
+        }
+    }
+    Ok(transform)
+}
+
+impl Anime {
+    /// Remove a systematic shift from the source before matching
+    ///
+    /// The shift of the `source` relative to the `target` is estimated with
+    /// [`estimate_shift()`] and applied to the source geometries. Matches
+    /// are then found between the corrected source and the target. Returns
+    /// the transformation that was applied along with the matched `Anime`.
+    pub fn new_aligned(
+        source: Vec<LineString>,
+        target: Vec<LineString>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+        options: &ShiftOptions,
+    ) -> Result<(Self, AffineTransform), AnimeError> {
+        let transform = estimate_shift(&source, &target, options)?;
+        let source = source.into_iter().map(|s| s.affine_transform(&transform));
+        let anime = Anime::new(
+            source,
+            target.into_iter(),
+            distance_tolerance,
+            angle_tolerance,
+        );
+        Ok((anime, transform))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{line_measures::Densify, Euclidean};
+    use geo_types::line_string;
+
+    /// A grid of horizontal and vertical lines with a few diagonals
+    fn network() -> Vec<LineString> {
+        let mut lines = Vec::new();
+        for k in 0..6 {
+            let v = k as f64 * 15.0;
+            lines.push(line_string![(x: 0.0, y: v), (x: 75.0, y: v)]);
+            lines.push(line_string![(x: v, y: 0.0), (x: v, y: 75.0)]);
+        }
+        lines.push(line_string![(x: 0.0, y: 0.0), (x: 75.0, y: 75.0)]);
+        lines.push(line_string![(x: 0.0, y: 75.0), (x: 75.0, y: 0.0)]);
+        // densify so that every line has many midpoints
+        lines
+            .into_iter()
+            .map(|l| l.densify::<Euclidean>(2.5))
+            .collect()
+    }
+
+    fn assert_close(a: Coord, b: Coord) {
+        assert!((a.x - b.x).hypot(a.y - b.y) < 1e-2, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn estimate_shift_recovers_translation() {
+        let target = network();
+        let shift = AffineTransform::translate(3.0, -2.0);
+        let source = target
+            .iter()
+            .map(|l| l.affine_transform(&shift.inverse().unwrap()))
+            .collect::<Vec<_>>();
+
+        let transform = estimate_shift(&source, &target, &ShiftOptions::default()).unwrap();
+        for c in [Coord { x: 0.0, y: 0.0 }, Coord { x: 50.0, y: 20.0 }] {
+            assert_close(transform.apply(c), shift.apply(c));
+        }
+    }
+
+    #[test]
+    fn estimate_shift_recovers_affine() {
+        let target = network();
+        let angle = 1.5_f64.to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let affine = AffineTransform::new(1.01 * cos, -sin, 2.0, sin, 0.99 * cos, -1.0);
+        let source = target
+            .iter()
+            .map(|l| l.affine_transform(&affine.inverse().unwrap()))
+            .collect::<Vec<_>>();
+
+        let options = ShiftOptions {
+            model: ShiftModel::Affine,
+            max_iterations: 100,
+            convergence: 1e-6,
+            ..Default::default()
+        };
+        let transform = estimate_shift(&source, &target, &options).unwrap();
+        for c in [
+            Coord { x: 0.0, y: 0.0 },
+            Coord { x: 75.0, y: 0.0 },
+            Coord { x: 30.0, y: 60.0 },
+        ] {
+            assert_close(transform.apply(c), affine.apply(c));
+        }
+    }
+}
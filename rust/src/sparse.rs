@@ -0,0 +1,222 @@
+use crate::{
+    model::MatchModel,
+    table::{match_table, MatchRow},
+    Anime, AnimeError, MatchesMap,
+};
+
+/// The value stored for each match in a weight matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weighting {
+    /// The shared length between source and target
+    SharedLength,
+    /// The shared length divided by the length of the source
+    SourceNormalised,
+    /// The shared length divided by the length of the target
+    TargetNormalised,
+}
+
+impl Weighting {
+    fn value(&self, row: &MatchRow) -> f64 {
+        match self {
+            Weighting::SharedLength => row.shared_len,
+            Weighting::SourceNormalised => row.source_weighted,
+            Weighting::TargetNormalised => row.target_weighted,
+        }
+    }
+}
+
+/// A target by source weight matrix in coordinate format
+///
+/// Each match is stored as a `(row, col, value)` triplet where the row is
+/// the target index and the column is the source index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CooMatrix {
+    /// Number of targets and number of sources
+    pub shape: (usize, usize),
+    pub rows: Vec<usize>,
+    pub cols: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+/// A target by source weight matrix in compressed sparse row format
+///
+/// The source indices and values of target `j` are stored in
+/// `indices[indptr[j]..indptr[j + 1]]` and `data[indptr[j]..indptr[j + 1]]`
+/// sorted by source index. Targets without matches are empty rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrMatrix {
+    /// Number of targets and number of sources
+    pub shape: (usize, usize),
+    pub indptr: Vec<usize>,
+    pub indices: Vec<usize>,
+    pub data: Vec<f64>,
+}
+
+/// Build a target by source weight matrix in coordinate format
+///
+/// The shape is `target_lens.len()` by `source_lens.len()`.
+pub fn coo_matrix(
+    matches: &MatchesMap,
+    source_lens: &[f64],
+    target_lens: &[f64],
+    weighting: Weighting,
+) -> CooMatrix {
+    let rows = match_table(matches, source_lens, target_lens);
+    CooMatrix {
+        shape: (target_lens.len(), source_lens.len()),
+        rows: rows.iter().map(|r| r.target_id).collect(),
+        cols: rows.iter().map(|r| r.source_id).collect(),
+        values: rows.iter().map(|r| weighting.value(r)).collect(),
+    }
+}
+
+/// Build a target by source weight matrix in compressed sparse row format
+///
+/// The shape is `target_lens.len()` by `source_lens.len()`.
+pub fn csr_matrix(
+    matches: &MatchesMap,
+    source_lens: &[f64],
+    target_lens: &[f64],
+    weighting: Weighting,
+) -> CsrMatrix {
+    coo_matrix(matches, source_lens, target_lens, weighting).to_csr()
+}
+
+impl CooMatrix {
+    /// Number of stored values
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Convert to compressed sparse row format
+    pub fn to_csr(&self) -> CsrMatrix {
+        let (n_rows, n_cols) = self.shape;
+        let mut order = (0..self.nnz()).collect::<Vec<_>>();
+        order.sort_by_key(|&k| (self.rows[k], self.cols[k]));
+
+        let mut indptr = vec![0; n_rows + 1];
+        for &r in &self.rows {
+            indptr[r + 1] += 1;
+        }
+        for j in 0..n_rows {
+            indptr[j + 1] += indptr[j];
+        }
+
+        CsrMatrix {
+            shape: (n_rows, n_cols),
+            indptr,
+            indices: order.iter().map(|&k| self.cols[k]).collect(),
+            data: order.iter().map(|&k| self.values[k]).collect(),
+        }
+    }
+}
+
+impl CsrMatrix {
+    /// Number of stored values
+    pub fn nnz(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Convert to coordinate format
+    pub fn to_coo(&self) -> CooMatrix {
+        let rows = self
+            .indptr
+            .windows(2)
+            .enumerate()
+            .flat_map(|(j, w)| std::iter::repeat_n(j, w[1] - w[0]))
+            .collect();
+        CooMatrix {
+            shape: self.shape,
+            rows,
+            cols: self.indices.clone(),
+            values: self.data.clone(),
+        }
+    }
+}
+
+impl Anime {
+    /// The matches as a target by source weight matrix in coordinate format
+    pub fn weights_coo(&self, weighting: Weighting) -> Result<CooMatrix, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        Ok(coo_matrix(
+            matches,
+            &self.source_lens,
+            &self.target_lens,
+            weighting,
+        ))
+    }
+
+    /// The matches as a target by source weight matrix in compressed sparse row format
+    pub fn weights_csr(&self, weighting: Weighting) -> Result<CsrMatrix, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        Ok(csr_matrix(
+            matches,
+            &self.source_lens,
+            &self.target_lens,
+            weighting,
+        ))
+    }
+}
+
+impl MatchModel {
+    /// The matches as a target by source weight matrix in coordinate format
+    pub fn weights_coo(&self, weighting: Weighting) -> CooMatrix {
+        coo_matrix(
+            &self.matches,
+            &self.source_lens,
+            &self.target_lens,
+            weighting,
+        )
+    }
+
+    /// The matches as a target by source weight matrix in compressed sparse row format
+    pub fn weights_csr(&self, weighting: Weighting) -> CsrMatrix {
+        csr_matrix(
+            &self.matches,
+            &self.source_lens,
+            &self.target_lens,
+            weighting,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candidate;
+
+    #[test]
+    fn csr_rows_are_sorted_and_round_trip_through_coo() {
+        // target 1 has no matches and the candidates of target 2 are unsorted
+        let matches = MatchesMap::from([
+            (0, vec![candidate(1, 2.0)]),
+            (2, vec![candidate(2, 3.0), candidate(0, 1.0)]),
+        ]);
+        let source_lens = [4.0, 4.0, 6.0];
+        let target_lens = [2.0, 5.0, 4.0];
+
+        let csr = csr_matrix(
+            &matches,
+            &source_lens,
+            &target_lens,
+            Weighting::SharedLength,
+        );
+        assert_eq!(csr.shape, (3, 3));
+        assert_eq!(csr.indptr, vec![0, 1, 1, 3]);
+        assert_eq!(csr.indices, vec![1, 0, 2]);
+        assert_eq!(csr.data, vec![2.0, 1.0, 3.0]);
+
+        let coo = csr.to_coo();
+        assert_eq!(coo.rows, vec![0, 2, 2]);
+        assert_eq!(coo.cols, vec![1, 0, 2]);
+        assert_eq!(coo.to_csr(), csr);
+
+        let normalised = csr_matrix(
+            &matches,
+            &source_lens,
+            &target_lens,
+            Weighting::SourceNormalised,
+        );
+        assert_eq!(normalised.data, vec![0.5, 0.25, 0.5]);
+    }
+}
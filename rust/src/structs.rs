@@ -3,12 +3,6 @@ use geo::{Line, Point};
 use rstar::RTreeObject;
 use rstar::AABB;
 
-// Currently unused
-// enum CrsType {
-//     Projected,
-//     Geographic,
-// }
-
 /// Represents a component `Line` of a target `LineString`
 ///
 /// The tuple stores the `Line` struct and the distance buffer to be used.
@@ -0,0 +1,48 @@
+use crate::{Anime, AnimeError, MatchesMap};
+
+/// A single row of the match table
+///
+/// `source_weighted` is the shared length divided by the length of the
+/// source and `target_weighted` is the shared length divided by the
+/// length of the target. These are the weights used by extensive and
+/// intensive interpolation respectively. `confidence` is the
+/// [`crate::MatchCandidate::confidence`] of the match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchRow {
+    pub target_id: usize,
+    pub source_id: usize,
+    pub shared_len: f64,
+    pub source_weighted: f64,
+    pub target_weighted: f64,
+    pub confidence: f64,
+}
+
+/// Flatten matches into a table ordered by target then source match order
+pub fn match_table(
+    matches: &MatchesMap,
+    source_lens: &[f64],
+    target_lens: &[f64],
+) -> Vec<MatchRow> {
+    matches
+        .iter()
+        .flat_map(|(j, cands)| {
+            let target_len = target_lens[*j];
+            cands.iter().map(move |ci| MatchRow {
+                target_id: *j,
+                source_id: ci.source_index,
+                shared_len: ci.shared_len,
+                source_weighted: ci.shared_len / source_lens[ci.source_index],
+                target_weighted: ci.shared_len / target_len,
+                confidence: ci.confidence,
+            })
+        })
+        .collect()
+}
+
+impl Anime {
+    /// The matches as a flat table. See [`match_table()`].
+    pub fn match_table(&self) -> Result<Vec<MatchRow>, AnimeError> {
+        let matches = self.matches.get().ok_or(AnimeError::MatchesNotFound)?;
+        Ok(match_table(matches, &self.source_lens, &self.target_lens))
+    }
+}
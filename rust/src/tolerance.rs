@@ -0,0 +1,188 @@
+use crate::{builder::MatchOptions, create_source_rtree, create_target_rtree, Anime, AnimeError};
+
+/// Per feature distance and angle tolerances
+///
+/// Each vector, when present, must have one tolerance per source or target
+/// feature. The tolerance of a source and target pair is the smaller of the
+/// source and target tolerances that are present. When neither is present the
+/// scalar `distance_tolerance` or `angle_tolerance` of [`Anime`] is used.
+///
+/// The envelope of each target component line is padded by its own distance
+/// tolerance. Targets without one are padded by the largest source distance
+/// tolerance or, when there are none, by the scalar `distance_tolerance`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FeatureTolerances {
+    pub source_distance: Option<Vec<f64>>,
+    pub target_distance: Option<Vec<f64>>,
+    pub source_angle: Option<Vec<f64>>,
+    pub target_angle: Option<Vec<f64>>,
+}
+
+fn pair_tolerance(
+    source: &Option<Vec<f64>>,
+    target: &Option<Vec<f64>>,
+    i: usize,
+    j: usize,
+    default: f64,
+) -> f64 {
+    let si = source.as_ref().and_then(|v| v.get(i).copied());
+    let tj = target.as_ref().and_then(|v| v.get(j).copied());
+    match (si, tj) {
+        (Some(s), Some(t)) => s.min(t),
+        (Some(s), None) => s,
+        (None, Some(t)) => t,
+        (None, None) => default,
+    }
+}
+
+impl FeatureTolerances {
+    /// Returns `true` if no per feature tolerances are set
+    pub fn is_empty(&self) -> bool {
+        self.source_distance.is_none()
+            && self.target_distance.is_none()
+            && self.source_angle.is_none()
+            && self.target_angle.is_none()
+    }
+
+    /// Distance tolerance between source `i` and target `j`
+    pub fn distance(&self, i: usize, j: usize, default: f64) -> f64 {
+        pair_tolerance(&self.source_distance, &self.target_distance, i, j, default)
+    }
+
+    /// Angle tolerance between source `i` and target `j`
+    pub fn angle(&self, i: usize, j: usize, default: f64) -> f64 {
+        pair_tolerance(&self.source_angle, &self.target_angle, i, j, default)
+    }
+
+    /// The largest source distance tolerance, if any are set
+    pub fn max_source_distance(&self) -> Option<f64> {
+        self.source_distance
+            .as_ref()?
+            .iter()
+            .copied()
+            .reduce(f64::max)
+    }
+
+    /// Envelope padding distance of target `j`
+    ///
+    /// Targets without their own distance tolerance are padded by the
+    /// largest source distance tolerance or, if there are none, by `default`.
+    /// The largest source tolerance is found on every call, so use
+    /// [`FeatureTolerances::padding_fn()`] to pad many targets.
+    pub fn padding(&self, j: usize, default: f64) -> f64 {
+        self.padding_fn(default)(j)
+    }
+
+    /// The envelope padding distance of each target. See [`FeatureTolerances::padding()`].
+    pub fn padding_fn(&self, default: f64) -> impl Fn(usize) -> f64 + '_ {
+        let fallback = self.max_source_distance().unwrap_or(default);
+        move |j| {
+            self.target_distance
+                .as_ref()
+                .and_then(|v| v.get(j).copied())
+                .unwrap_or(fallback)
+        }
+    }
+
+    pub(crate) fn validate(&self, n_source: usize, n_target: usize) -> Result<(), AnimeError> {
+        let check = |v: &Option<Vec<f64>>, n: usize| v.as_ref().is_none_or(|v| v.len() == n);
+        let is_valid = check(&self.source_distance, n_source)
+            && check(&self.source_angle, n_source)
+            && check(&self.target_distance, n_target)
+            && check(&self.target_angle, n_target);
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(AnimeError::IncorrectToleranceLength)
+        }
+    }
+}
+
+impl Anime {
+    /// Load source and target geometries with per feature tolerances
+    ///
+    /// Like [`Anime::load_geometries()`] but the `distance_tolerance` and
+    /// `angle_tolerance` can be overridden per feature. See [`FeatureTolerances`].
+    pub fn load_geometries_with_tolerances(
+        source: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
+        target: impl Iterator<Item = impl Into<geo_types::MultiLineString>>,
+        distance_tolerance: f64,
+        angle_tolerance: f64,
+        feature_tolerances: FeatureTolerances,
+    ) -> Result<Self, AnimeError> {
+        let mut source_lens = Vec::new();
+        let mut target_lens = Vec::new();
+        let source_tree = create_source_rtree(source, &mut source_lens);
+        let target_tree = create_target_rtree(
+            target,
+            &mut target_lens,
+            feature_tolerances.padding_fn(distance_tolerance),
+        );
+        feature_tolerances.validate(source_lens.len(), target_lens.len())?;
+
+        Ok(Self::from_trees(
+            distance_tolerance,
+            angle_tolerance,
+            feature_tolerances,
+            MatchOptions::default(),
+            (source_tree, source_lens),
+            (target_tree, target_lens),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+
+    #[test]
+    fn pair_tolerance_is_the_smaller_of_the_pair() {
+        let tol = FeatureTolerances {
+            source_distance: Some(vec![2.0, 8.0]),
+            target_distance: Some(vec![5.0]),
+            source_angle: Some(vec![20.0, 30.0]),
+            ..Default::default()
+        };
+        assert_eq!(tol.distance(0, 0, 10.0), 2.0);
+        assert_eq!(tol.distance(1, 0, 10.0), 5.0);
+        assert_eq!(tol.angle(1, 0, 5.0), 30.0);
+        assert_eq!(FeatureTolerances::default().angle(0, 0, 5.0), 5.0);
+    }
+
+    #[test]
+    fn targets_fall_back_to_the_largest_source_tolerance() {
+        let tol = FeatureTolerances {
+            source_distance: Some(vec![2.0, 8.0, 3.0]),
+            target_distance: Some(vec![5.0]),
+            ..Default::default()
+        };
+        let padding = tol.padding_fn(10.0);
+        assert_eq!(padding(0), 5.0);
+        assert_eq!(padding(1), 8.0);
+        assert_eq!(tol.padding(1, 10.0), 8.0);
+        assert_eq!(FeatureTolerances::default().padding(0, 10.0), 10.0);
+    }
+
+    #[test]
+    fn tolerances_must_have_one_value_per_feature() {
+        let lines = || [line_string![(x: 0.0, y: 0.0), (x: 10.0, y: 0.0)]].into_iter();
+        let load = |feature_tolerances| {
+            Anime::load_geometries_with_tolerances(lines(), lines(), 10.0, 5.0, feature_tolerances)
+        };
+        assert!(load(FeatureTolerances {
+            target_angle: Some(vec![5.0]),
+            ..Default::default()
+        })
+        .is_ok());
+        assert!(matches!(
+            load(FeatureTolerances {
+                source_distance: Some(vec![1.0, 2.0]),
+                ..Default::default()
+            }),
+            Err(AnimeError::IncorrectToleranceLength)
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    linear::{line_substring, locate_point},
+    Anime, AnimeError,
+};
+use geo::{line_measures::Densify, Euclidean, FrechetDistance, HausdorffDistance};
+use geo_types::{LineString, MultiLineString};
+
+/// Shape similarity metric between matched portions of two features
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeMetric {
+    /// Discrete Fréchet distance, sensitive to the order of vertices
+    Frechet,
+    /// Hausdorff distance, the largest distance from a point on one portion
+    /// to the closest point on the other
+    Hausdorff,
+}
+
+/// What to do with matches whose shape distance exceeds the threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeAction {
+    /// Remove the match
+    Reject,
+    /// Keep the match and report it
+    Flag,
+}
+
+/// Options for validating matches by shape similarity
+#[derive(Debug, Clone)]
+pub struct ShapeValidation {
+    pub metric: ShapeMetric,
+    /// Matches with a shape distance greater than this are rejected or flagged
+    pub threshold: f64,
+    pub action: ShapeAction,
+    /// Portions are densified to this maximum segment length before
+    /// computing the discrete metric
+    pub max_segment_length: f64,
+}
+
+/// Matched portion of `line` as seen from `other`
+///
+/// The portion spans the projections of the vertices of `other` onto `line`.
+/// It follows the direction of `other` if `follow_other` is `true` and the
+/// direction of `line` otherwise.
+fn matched_portion(line: &LineString, other: &LineString, follow_other: bool) -> LineString {
+    let fracs = other
+        .coords()
+        .map(|c| locate_point(line, *c))
+        .collect::<Vec<_>>();
+    let lo = fracs.iter().copied().fold(f64::INFINITY, f64::min);
+    let hi = fracs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let is_reversed = follow_other && fracs.first() > fracs.last();
+
+    let coords = if is_reversed {
+        line_substring(line, hi, lo)
+    } else {
+        line_substring(line, lo, hi)
+    };
+    LineString::new(coords)
+}
+
+/// Shape distance between the matched portions of a source and a target
+pub fn shape_distance(
+    source: &LineString,
+    target: &LineString,
+    metric: ShapeMetric,
+    max_segment_length: f64,
+) -> f64 {
+    // both portions follow the direction of the source
+    let sp = matched_portion(source, target, false);
+    let tp = matched_portion(target, source, true);
+    let (sp, tp) = if max_segment_length > 0.0 {
+        (
+            sp.densify::<Euclidean>(max_segment_length),
+            tp.densify::<Euclidean>(max_segment_length),
+        )
+    } else {
+        (sp, tp)
+    };
+
+    match metric {
+        ShapeMetric::Frechet => sp.frechet_distance(&tp),
+        ShapeMetric::Hausdorff => sp.hausdorff_distance(&tp),
+    }
+}
+
+/// Shape distance between two multi-part features
+///
+/// This is the smallest [`shape_distance()`] between any component of
+/// `source` and any component of `target`, so only the best matching pair of
+/// components is compared.
+fn multi_shape_distance(
+    source: &MultiLineString,
+    target: &MultiLineString,
+    metric: ShapeMetric,
+    max_segment_length: f64,
+) -> f64 {
+    source
+        .iter()
+        .flat_map(|s| {
+            target
+                .iter()
+                .map(move |t| shape_distance(s, t, metric, max_segment_length))
+        })
+        .reduce(f64::min)
+        .unwrap_or(f64::NAN)
+}
+
+impl Anime {
+    /// Validate matches by the shape similarity of the matched portions
+    ///
+    /// For each match the matched portion of the source and target features
+    /// are compared with a [`ShapeMetric`]. The result is stored in
+    /// [`crate::MatchCandidate::shape_distance`]. Matches above the threshold
+    /// are removed when the action is [`ShapeAction::Reject`].
+    ///
+    /// Multi-part features are compared by their best matching pair of
+    /// components.
+    ///
+    /// `source` and `target` must be the geometries used to create `self`.
+    /// Matches found with [`crate::builder::CrsMode::Geographic`] can't be
+    /// validated. Returns the `(target_index, source_index)` pairs above the threshold,
+    /// including those whose shape distance is NaN.
+    pub fn validate_shapes(
+        &mut self,
+        source: &[MultiLineString],
+        target: &[MultiLineString],
+        validation: &ShapeValidation,
+    ) -> Result<Vec<(usize, usize)>, AnimeError> {
+        if source.len() != self.source_lens.len() || target.len() != self.target_lens.len() {
+            return Err(AnimeError::IncorrectGeometryLength);
+        }
+        self.require_projected("validate_shapes")?;
+        let matches = self.matches.get_mut().ok_or(AnimeError::MatchesNotFound)?;
+
+        // NaN distances can't be compared so they exceed any threshold
+        let exceeds = |d: f64| d.is_nan() || d > validation.threshold;
+        let mut exceeded = Vec::new();
+        for (j, cands) in matches.iter_mut() {
+            for ci in cands.iter_mut() {
+                let d = multi_shape_distance(
+                    &source[ci.source_index],
+                    &target[*j],
+                    validation.metric,
+                    validation.max_segment_length,
+                );
+                ci.shape_distance = Some(d);
+                if exceeds(d) {
+                    exceeded.push((*j, ci.source_index));
+                }
+            }
+        }
+
+        if validation.action == ShapeAction::Reject {
+            matches.retain(|_, cands| {
+                cands.retain(|ci| !ci.shape_distance.is_some_and(exceeds));
+                !cands.is_empty()
+            });
+        }
+        Ok(exceeded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::line_string;
+
+    fn segment(y: f64) -> MultiLineString {
+        MultiLineString::new(vec![line_string![(x: 0.0, y: y), (x: 10.0, y: y)]])
+    }
+
+    #[test]
+    fn matches_above_the_threshold_are_flagged_or_rejected() {
+        let source = vec![segment(0.0), segment(100.0), segment(200.0)];
+        let target = vec![segment(0.2), segment(101.5), segment(200.5)];
+        // a source without components has a NaN shape distance
+        let mut validated = source.clone();
+        validated[2] = MultiLineString::new(vec![]);
+
+        let validate = |action| {
+            let mut anime = Anime::new(source.iter().cloned(), target.iter().cloned(), 2.0, 5.0);
+            let validation = ShapeValidation {
+                metric: ShapeMetric::Hausdorff,
+                threshold: 1.0,
+                action,
+                max_segment_length: 1.0,
+            };
+            let exceeded = anime
+                .validate_shapes(&validated, &target, &validation)
+                .unwrap();
+            (anime, exceeded)
+        };
+
+        let (flagged, exceeded) = validate(ShapeAction::Flag);
+        assert_eq!(exceeded, vec![(1, 1), (2, 2)]);
+        let matches = flagged.matches.get().unwrap();
+        assert_eq!(matches.len(), 3);
+        let d = |j: usize| matches[&j][0].shape_distance.unwrap();
+        assert!((d(0) - 0.2).abs() < 1e-9);
+        assert!((d(1) - 1.5).abs() < 1e-9);
+        assert!(d(2).is_nan());
+
+        let (rejected, exceeded) = validate(ShapeAction::Reject);
+        assert_eq!(exceeded, vec![(1, 1), (2, 2)]);
+        let matches = rejected.matches.get().unwrap();
+        assert_eq!(matches.keys().copied().collect::<Vec<_>>(), vec![0]);
+    }
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "cli")]
+
+use std::{path::PathBuf, process::Command};
+
+fn extdata(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../r/inst/extdata")
+        .join(name)
+}
+
+fn anime(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_anime"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn match_args(extra: &[&str]) -> Vec<String> {
+    let mut args = vec![
+        "match".to_string(),
+        "--source".to_string(),
+        extdata("princes_street_minimal.geojson")
+            .display()
+            .to_string(),
+        "--target".to_string(),
+        extdata("princes_street_minimal_x_1.geojson")
+            .display()
+            .to_string(),
+    ];
+    args.extend(extra.iter().map(|a| a.to_string()));
+    args
+}
+
+#[test]
+fn match_writes_a_table_in_meters() {
+    let args = match_args(&["--crs", "geographic", "-d", "10", "-a", "10"]);
+    let out = anime(&args.iter().map(String::as_str).collect::<Vec<_>>());
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(
+        lines.next(),
+        Some("target_id,source_id,shared_len,weight,confidence")
+    );
+    let shared_lens = lines
+        .map(|l| l.split(',').nth(2).unwrap().parse::<f64>().unwrap())
+        .collect::<Vec<_>>();
+    assert!(!shared_lens.is_empty());
+    // degrees would be far smaller than a meter
+    assert!(shared_lens.iter().any(|l| *l > 1.0));
+}
+
+#[test]
+fn invalid_tolerances_are_reported() {
+    for d in ["NaN", "inf", "0"] {
+        let args = match_args(&["-d", d]);
+        let out = anime(&args.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(out.status.code(), Some(1));
+        let stderr = String::from_utf8(out.stderr).unwrap();
+        assert!(stderr.contains("distance_tolerance"), "{stderr}");
+    }
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "flatgeobuf")]
+
+use anime::{
+    io::flatgeobuf::{read_flatgeobuf, read_flatgeobuf_file, write_flatgeobuf},
+    Anime,
+};
+use geo::BoundingRect;
+use std::{fs::File, io::Cursor, path::PathBuf};
+
+const SOURCES: &str = "maine-tigris-sources.fgb";
+const TARGETS: &str = "maine-osm-targets.fgb";
+
+fn extdata(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../r/inst/extdata")
+        .join(name)
+}
+
+#[test]
+fn maine_features_are_read() {
+    let sources = read_flatgeobuf_file(extdata(SOURCES), None).unwrap();
+    let targets = read_flatgeobuf_file(extdata(TARGETS), None).unwrap();
+    assert_eq!(sources.len(), 8);
+    assert_eq!(targets.len(), 32);
+    assert!(sources.attributes.contains_key("FULLNAME"));
+    assert!(targets.attributes.contains_key("highway"));
+    assert!(sources
+        .attributes
+        .values()
+        .all(|v| v.len() == sources.len()));
+}
+
+#[test]
+fn maine_matches_stream_from_files() {
+    let open = |name| File::open(extdata(name)).unwrap();
+    let anime = Anime::from_flatgeobuf(open(SOURCES), open(TARGETS), 10.0, 5.0, None).unwrap();
+
+    let sources = read_flatgeobuf_file(extdata(SOURCES), None).unwrap();
+    let targets = read_flatgeobuf_file(extdata(TARGETS), None).unwrap();
+    let expected = Anime::new(
+        sources.geometries.into_iter(),
+        targets.geometries.into_iter(),
+        10.0,
+        5.0,
+    );
+    assert_eq!(anime.source_lens, expected.source_lens);
+    assert_eq!(anime.target_lens, expected.target_lens);
+
+    let summary = |a: &Anime| {
+        a.match_table()
+            .unwrap()
+            .iter()
+            .map(|r| (r.target_id, r.source_id, r.shared_len))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(summary(&anime).len(), 23);
+    assert_eq!(summary(&anime), summary(&expected));
+}
+
+#[test]
+fn maine_matches_are_filtered_by_bbox() {
+    let sources = read_flatgeobuf_file(extdata(SOURCES), None).unwrap();
+    let bbox = sources.geometries[0].bounding_rect().unwrap();
+    let in_bbox = read_flatgeobuf_file(extdata(SOURCES), Some(bbox)).unwrap();
+    assert!(!in_bbox.is_empty() && in_bbox.len() < sources.len());
+
+    let open = |name| File::open(extdata(name)).unwrap();
+    let anime =
+        Anime::from_flatgeobuf(open(SOURCES), open(TARGETS), 10.0, 5.0, Some(bbox)).unwrap();
+    let targets = read_flatgeobuf_file(extdata(TARGETS), Some(bbox)).unwrap();
+    assert_eq!(anime.source_lens.len(), in_bbox.len());
+    assert_eq!(anime.target_lens.len(), targets.len());
+    assert!(!anime.match_table().unwrap().is_empty());
+}
+
+#[test]
+fn maine_features_round_trip() {
+    let targets = read_flatgeobuf_file(extdata(TARGETS), None).unwrap();
+    let mut buf = Vec::new();
+    write_flatgeobuf(&targets, "targets", &mut buf).unwrap();
+    let read = read_flatgeobuf(Cursor::new(buf), None).unwrap();
+
+    assert_eq!(read.geometries, targets.geometries);
+    assert_eq!(read.attributes, targets.attributes);
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "geojson")]
+
+use anime::{
+    builder::{AnimeBuilder, CrsMode},
+    interpolate::Tensive,
+    io::geojson::read_geojson_file,
+};
+use std::path::PathBuf;
+
+fn extdata(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../r/inst/extdata")
+        .join(name)
+}
+
+#[test]
+fn princes_street_minimal_matches() {
+    let source = read_geojson_file(extdata("princes_street_minimal.geojson")).unwrap();
+    let target = read_geojson_file(extdata("princes_street_minimal_x_1.geojson")).unwrap();
+    assert_eq!(source.len(), 4);
+
+    let matched = AnimeBuilder::new(
+        source.geometries.into_iter(),
+        target.geometries.iter().cloned(),
+    )
+    .distance_tolerance(10.0)
+    .angle_tolerance(10.0)
+    .crs_mode(CrsMode::Geographic)
+    .find_matches()
+    .unwrap();
+
+    let rows = matched.match_table();
+    assert!(!rows.is_empty());
+    for j in 0..target.len() {
+        assert!(
+            rows.iter().any(|r| r.target_id == j),
+            "target {j} is unmatched"
+        );
+    }
+
+    let values = matched
+        .interpolate(&[1.0, 2.0, 3.0, 4.0], Tensive::In)
+        .unwrap();
+    assert!(values.iter().all(|v| (1.0..=4.0).contains(&v.value)));
+}